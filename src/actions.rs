@@ -0,0 +1,216 @@
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+
+use crate::testcases::{RepoResult, TcResult};
+
+// grade-results.json inside an artifact. Older workflows write only "grade";
+// newer ones also list each test the way a local class run records it.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ArtifactResults {
+    #[serde(default)] pub grade: Option<f64>,
+    #[serde(default, alias = "build_error", skip_serializing_if = "Option::is_none")] pub build_err: Option<String>,
+    #[serde(default, alias = "tests")] pub results: Vec<ArtifactTest>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ArtifactTest {
+    #[serde(alias = "name")] pub test: String,
+    #[serde(default)] pub rubric: f64,
+    #[serde(default)] pub score: f64,
+    #[serde(default, alias = "error", skip_serializing_if = "Option::is_none")] pub test_err: Option<String>,
+}
+
+impl ArtifactResults {
+    pub fn from_repo_result(rr: &RepoResult) -> Self {
+        ArtifactResults {
+            grade: Some(rr.score as f64),
+            build_err: rr.build_err.clone(),
+            results: rr.results.iter().map(|t| ArtifactTest {
+                test: t.test.clone(),
+                rubric: t.rubric as f64,
+                score: t.score as f64,
+                test_err: t.test_err.clone(),
+            }).collect(),
+        }
+    }
+
+    // Fill in results, build error and score; scores are rounded to i64 to fit the current schema
+    pub fn apply(self, rr: &mut RepoResult) {
+        rr.results = self.results.into_iter().map(|t| TcResult {
+            rubric: t.rubric.round() as i64,
+            score: t.score.round() as i64,
+            test: t.test,
+            test_err: t.test_err,
+            ..Default::default()
+        }).collect();
+        rr.build_err = self.build_err;
+        let earned: i64 = rr.results.iter().map(|t| t.score).sum();
+        rr.score = self.grade.map(|g| g.round() as i64).unwrap_or(earned);
+    }
+
+    pub fn write(&self, path: &str) -> anyhow::Result<()> {
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+// Markdown for the Actions job summary page
+pub fn step_summary(project: &str, rr: &RepoResult) -> String {
+    let avail: i64 = rr.results.iter().map(|t| t.rubric).sum();
+    let mut out = format!("## {} results: {}/{}\n\n", project, rr.score, avail);
+    if let Some(be) = &rr.build_err {
+        out.push_str(&format!("**{}**\n\n", be.trim_end()));
+    }
+    if !rr.results.is_empty() {
+        out.push_str("| Test | Score | Error |\n|---|---|---|\n");
+        for t in &rr.results {
+            let icon = if t.score == t.rubric { "✅" } else { "❌" };
+            // Keep each error on one table row
+            let err = t.test_err.as_deref().unwrap_or("").replace('|', "\\|").replace('\n', " ");
+            out.push_str(&format!("| {} {} | {}/{} | {} |\n", icon, t.test, t.score, t.rubric, err));
+        }
+    }
+    out
+}
+
+// Appends to $GITHUB_STEP_SUMMARY; does nothing outside Actions
+pub fn write_step_summary(project: &str, rr: &RepoResult) -> anyhow::Result<bool> {
+    let Ok(path) = std::env::var("GITHUB_STEP_SUMMARY") else { return Ok(false) };
+    if path.is_empty() { return Ok(false); }
+    let mut f = OpenOptions::new().create(true).append(true).open(&path)?;
+    f.write_all(step_summary(project, rr).as_bytes())?;
+    Ok(true)
+}
+
+// Check run annotation; level is notice, warning or failure
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Annotation {
+    pub path: String,
+    pub start_line: u32,
+    pub end_line: u32,
+    pub annotation_level: String,
+    #[serde(skip_serializing_if = "Option::is_none")] pub title: Option<String>,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CheckRunOutput {
+    pub title: String,
+    pub summary: String,
+    pub annotations: Vec<Annotation>,
+}
+
+// GitHub rejects check run text longer than 64K
+const CHECK_TEXT_MAX: usize = 65_000;
+
+fn truncate(s: &str, max: usize) -> String {
+    if s.len() <= max { return s.to_string(); }
+    let mut end = max;
+    while !s.is_char_boundary(end) { end -= 1; }
+    format!("{}...", &s[..end])
+}
+
+// file:line[:col]: error|warning: message lines from gcc, clang and as, with paths made
+// relative to the repo root. Paths outside $GITHUB_WORKSPACE cannot be annotated and are skipped.
+pub fn compiler_annotations(output: &str, subdir: Option<&str>) -> Vec<Annotation> {
+    let re = regex::Regex::new(r"^([^:\s][^:]*):(\d+):(?:\d+:)?\s*((?i:fatal error|error|warning)):\s*(.+)$").unwrap();
+    let workspace = std::env::var("GITHUB_WORKSPACE").ok().filter(|w| !w.is_empty());
+    let mut out: Vec<Annotation> = vec![];
+    for line in output.lines() {
+        let Some(c) = re.captures(line.trim_end()) else { continue };
+        let file = c[1].trim_start_matches("./");
+        let path = if file.starts_with('/') {
+            match workspace.as_deref().and_then(|w| file.strip_prefix(w)) {
+                Some(rel) => rel.trim_start_matches('/').to_string(),
+                None => continue,
+            }
+        } else {
+            match subdir { Some(d) => format!("{}/{}", d.trim_end_matches('/'), file), None => file.to_string() }
+        };
+        let line_no: u32 = c[2].parse().unwrap_or(1);
+        let level = if c[3].eq_ignore_ascii_case("warning") { "warning" } else { "failure" };
+        let a = Annotation { path, start_line: line_no, end_line: line_no, annotation_level: level.into(), title: None, message: c[4].to_string() };
+        if !out.contains(&a) { out.push(a); }
+    }
+    out
+}
+
+// Conclusion and output for a check run on the graded commit. Failing tests are annotated on
+// test_path (the workflow file), since the tests themselves live outside the student repo.
+pub fn check_run(project: &str, rr: &RepoResult, build_output: Option<&str>, subdir: Option<&str>, test_path: &str) -> (&'static str, CheckRunOutput) {
+    let avail: i64 = rr.results.iter().map(|t| t.rubric).sum();
+    let passed = rr.build_err.is_none() && rr.results.iter().all(|t| t.score >= t.rubric);
+    let mut annotations = build_output.map(|o| compiler_annotations(o, subdir)).unwrap_or_default();
+    for t in rr.results.iter().filter(|t| t.score < t.rubric) {
+        annotations.push(Annotation {
+            path: test_path.to_string(),
+            start_line: 1,
+            end_line: 1,
+            annotation_level: "failure".into(),
+            title: Some(format!("{} ({}/{})", t.test, t.score, t.rubric)),
+            message: truncate(t.test_err.as_deref().unwrap_or("Output did not match"), 4000),
+        });
+    }
+    let output = CheckRunOutput {
+        title: format!("{}/{} points", rr.score, avail),
+        summary: truncate(&step_summary(project, rr), CHECK_TEXT_MAX),
+        annotations,
+    };
+    (if passed { "success" } else { "failure" }, output)
+}
+
+const WORKFLOW_TEMPLATE: &str = r#"name: Grade
+
+on:
+  push:
+  workflow_dispatch:
+
+jobs:
+  grade:
+    runs-on: ubuntu-latest
+    timeout-minutes: 15
+{permissions}    steps:
+      - uses: actions/checkout@v4
+
+      # The tests repo is usually private; TESTS_TOKEN needs read access to it
+      - uses: actions/checkout@v4
+        with:
+          repository: {tests_repo}
+          token: ${{ secrets.TESTS_TOKEN }}
+          path: .grade/tests
+
+      - uses: dtolnay/rust-toolchain@stable
+
+      - name: Install grade-rs
+        run: cargo install --locked --git {install_from} autograder_rust
+
+      - name: Run tests
+        env:
+          GRADE_CONFIG_DIR: ${{ github.workspace }}/.grade
+{token_env}        run: |
+          printf '[Test]\ntests_path = "%s/.grade/tests"\n' "$GITHUB_WORKSPACE" > .grade/config.toml
+          grade-rs test -p {project} --artifact grade-results.json{check_flag}
+
+      - uses: actions/upload-artifact@v4
+        if: always()
+        with:
+          name: {artifact_name}
+          path: grade-results.json
+"#;
+
+// A workflow that runs `grade-rs test` and uploads the artifact `class -g` reads.
+// With check_run it also publishes a check run, which needs checks: write.
+pub fn workflow_yaml(project: &str, tests_repo: &str, install_from: &str, artifact_name: &str, check_run: bool) -> String {
+    let (permissions, token_env, check_flag) = if check_run {
+        ("    permissions:\n      contents: read\n      checks: write\n", "          GITHUB_TOKEN: ${{ secrets.GITHUB_TOKEN }}\n", " --check-run")
+    } else { ("", "", "") };
+    WORKFLOW_TEMPLATE
+        .replace("{permissions}", permissions)
+        .replace("{token_env}", token_env)
+        .replace("{check_flag}", check_flag)
+        .replace("{project}", project)
+        .replace("{tests_repo}", tests_repo)
+        .replace("{install_from}", install_from)
+        .replace("{artifact_name}", artifact_name)
+}
@@ -0,0 +1,142 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::io::{Cursor, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::files::sha256_file;
+use crate::util::print_yellow;
+
+const MANIFEST: &str = "manifest.json";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ManifestRepo {
+    pub student: String,
+    // Top-level directory holding this repo's files in the archive
+    pub path: String,
+    pub commit: String,
+    pub commit_date: String,
+    #[serde(skip_serializing_if = "Option::is_none")] pub score: Option<i64>,
+    pub files: usize,
+    // Over "path sha256" lines of the repo's files, sorted by path
+    pub sha256: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Manifest {
+    pub project: String,
+    #[serde(skip_serializing_if = "Option::is_none")] pub suffix: Option<String>,
+    pub created: String,
+    #[serde(skip_serializing_if = "Option::is_none")] pub tests_sha256: Option<String>,
+    pub repos: Vec<ManifestRepo>,
+}
+
+fn digest(files: &BTreeMap<String, Vec<u8>>) -> String {
+    let mut h = Sha256::new();
+    for (name, data) in files {
+        let fh: String = Sha256::digest(data).iter().map(|b| format!("{:02x}", b)).collect();
+        h.update(format!("{} {}\n", name, fh));
+    }
+    h.finalize().iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn git_text(local: &Path, args: &[&str]) -> anyhow::Result<String> {
+    let out = Command::new("git").args(args).current_dir(local).output()?;
+    if !out.status.success() { anyhow::bail!("git {} failed: {}", args[0], String::from_utf8_lossy(&out.stderr).trim()); }
+    Ok(String::from_utf8_lossy(&out.stdout).trim().to_string())
+}
+
+// Tracked files at HEAD via git archive, so build products and untracked notes are left out.
+// Values are (unix mode, contents).
+fn committed_files(local: &Path) -> anyhow::Result<BTreeMap<String, (u32, Vec<u8>)>> {
+    let out = Command::new("git").args(["archive", "--format=tar", "HEAD"]).current_dir(local).output()?;
+    if !out.status.success() { anyhow::bail!("git archive failed: {}", String::from_utf8_lossy(&out.stderr).trim()); }
+    let mut tar = tar::Archive::new(Cursor::new(out.stdout));
+    let mut files = BTreeMap::new();
+    for entry in tar.entries()? {
+        let mut entry = entry?;
+        if entry.header().entry_type() != tar::EntryType::Regular { continue; }
+        let name = entry.path()?.to_string_lossy().to_string();
+        let mode = entry.header().mode().unwrap_or(0o644);
+        let mut data = vec![];
+        entry.read_to_end(&mut data)?;
+        files.insert(name, (mode, data));
+    }
+    Ok(files)
+}
+
+// Pack each (student, repo dir) at its HEAD commit into one zip with a manifest.
+// Repos that are missing or have no commits are skipped with a warning.
+pub fn create_archive(out: &Path, project: &str, suffix: Option<&str>, repos: &[(String, PathBuf)], scores: &HashMap<String, i64>, tests_toml: &Path) -> anyhow::Result<Manifest> {
+    let mut zip = zip::ZipWriter::new(File::create(out)?);
+    let mut manifest = Manifest {
+        project: project.to_string(),
+        suffix: suffix.map(|s| s.to_string()),
+        created: chrono::Local::now().to_rfc3339(),
+        tests_sha256: sha256_file(tests_toml).ok(),
+        repos: vec![],
+    };
+    for (student, local) in repos {
+        let dir = local.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_else(|| student.clone());
+        let files = match committed_files(local) {
+            Ok(f) => f,
+            Err(e) => { print_yellow(&format!("Skipping {}: {}\n", local.display(), e)); continue; }
+        };
+        let mut contents = BTreeMap::new();
+        for (name, (mode, data)) in files {
+            let path = format!("{}/{}", dir, name);
+            let opts = zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Deflated).unix_permissions(mode);
+            zip.start_file(path, opts)?;
+            zip.write_all(&data)?;
+            contents.insert(name, data);
+        }
+        manifest.repos.push(ManifestRepo {
+            student: student.clone(),
+            path: dir,
+            commit: git_text(local, &["rev-parse", "HEAD"])?,
+            commit_date: git_text(local, &["log", "-1", "--format=%cI", "HEAD"])?,
+            score: scores.get(student).copied(),
+            files: contents.len(),
+            sha256: digest(&contents),
+        });
+    }
+    zip.start_file(MANIFEST, zip::write::FileOptions::default())?;
+    zip.write_all(serde_json::to_string_pretty(&manifest)?.as_bytes())?;
+    zip.finish()?;
+    Ok(manifest)
+}
+
+// Problems found when rechecking an archive against its manifest; empty means it verified
+pub fn verify_archive(path: &Path) -> anyhow::Result<(Manifest, Vec<String>)> {
+    let mut zip = zip::ZipArchive::new(File::open(path)?)?;
+    let manifest: Manifest = {
+        let mut f = zip.by_name(MANIFEST).map_err(|_| anyhow::anyhow!("{} has no {}", path.display(), MANIFEST))?;
+        let mut s = String::new();
+        f.read_to_string(&mut s)?;
+        serde_json::from_str(&s)?
+    };
+    // Top-level dir -> (relative name -> contents)
+    let mut by_dir: BTreeMap<String, BTreeMap<String, Vec<u8>>> = BTreeMap::new();
+    for i in 0..zip.len() {
+        let mut f = zip.by_index(i)?;
+        if f.is_dir() || f.name() == MANIFEST { continue; }
+        let name = f.name().to_string();
+        let (dir, rel) = name.split_once('/').unwrap_or(("", name.as_str()));
+        let mut data = vec![];
+        f.read_to_end(&mut data)?;
+        by_dir.entry(dir.to_string()).or_default().insert(rel.to_string(), data);
+    }
+    let mut problems = vec![];
+    for r in &manifest.repos {
+        match by_dir.remove(&r.path) {
+            None => problems.push(format!("{}: no files in archive", r.student)),
+            Some(files) if files.len() != r.files => problems.push(format!("{}: {} files, manifest lists {}", r.student, files.len(), r.files)),
+            Some(files) if digest(&files) != r.sha256 => problems.push(format!("{}: contents do not match manifest checksum", r.student)),
+            Some(_) => {}
+        }
+    }
+    for dir in by_dir.keys() { problems.push(format!("{}: not listed in manifest", dir)); }
+    Ok((manifest, problems))
+}
@@ -0,0 +1,47 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+
+use crate::testcases::RepoResult;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CacheEntry {
+    pub head: String,
+    pub result: RepoResult,
+}
+
+// Results from previous class runs, valid only while the tests are unchanged
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ResultCache {
+    pub tests_hash: String,
+    pub entries: BTreeMap<String, CacheEntry>,
+}
+
+impl ResultCache {
+    // Stored beside the class JSON; "projx.json" -> "projx.cache"
+    pub fn path_for(class_json: &str) -> String {
+        format!("{}.cache", class_json.strip_suffix(".json").unwrap_or(class_json))
+    }
+
+    // A missing or unreadable cache, or one built from different tests, starts empty
+    pub fn load(path: &str, tests_hash: &str) -> Self {
+        let cached: Option<ResultCache> = fs::read_to_string(path).ok().and_then(|s| serde_json::from_str(&s).ok());
+        match cached {
+            Some(c) if c.tests_hash == tests_hash => c,
+            _ => ResultCache { tests_hash: tests_hash.to_string(), entries: BTreeMap::new() },
+        }
+    }
+
+    pub fn get(&self, student: &str, head: &str) -> Option<&RepoResult> {
+        self.entries.get(student).filter(|e| e.head == head).map(|e| &e.result)
+    }
+
+    pub fn insert(&mut self, student: &str, head: &str, result: &RepoResult) {
+        self.entries.insert(student.to_string(), CacheEntry { head: head.to_string(), result: result.clone() });
+    }
+
+    pub fn save(&self, path: &str) -> anyhow::Result<()> {
+        fs::write(path, serde_json::to_string(self)?)?;
+        Ok(())
+    }
+}
@@ -1,9 +1,12 @@
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use std::collections::HashMap;
-use std::fs;
+use std::fs::{self, OpenOptions};
+use std::path::{Path, PathBuf};
 
 use crate::config::{CanvasCfg, CanvasMapperCfg};
-use crate::util::{expand_tilde, print_green, print_red, print_yellow};
+use crate::http::{Http, HttpOpts};
+use crate::util::{expand_tilde, print_green, print_justified, print_red, print_yellow};
 use std::io::{Read, Write};
 
 pub struct CanvasMapper {
@@ -36,26 +39,71 @@ impl CanvasMapper {
     pub fn lookup(&self, github: &str) -> Option<String> { self.map.get(github).cloned() }
 }
 
+// One line of the upload audit log, written for every grade sent to Canvas
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub run_id: String,
+    pub timestamp: String,
+    pub course_id: i64,
+    #[serde(default)] pub course: String,
+    pub assignment_id: i64,
+    pub user_id: i64,
+    pub old_score: Option<f64>,
+    // None when an undo cleared a grade that did not exist before
+    pub new_score: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")] pub comment_sha256: Option<String>,
+    pub ok: bool,
+    // Set on entries written by `upload --undo`
+    #[serde(default, skip_serializing_if = "Option::is_none")] pub undo_of: Option<String>,
+}
+
+struct AuditLog {
+    path: PathBuf,
+    run_id: String,
+    undo_of: Option<String>,
+}
+
+pub fn read_audit_log(path: &Path) -> anyhow::Result<Vec<AuditEntry>> {
+    let data = fs::read_to_string(path).map_err(|e| anyhow::anyhow!("{}: {}", path.display(), e))?;
+    data.lines().filter(|l| !l.trim().is_empty())
+        .map(|l| serde_json::from_str(l).map_err(|e| anyhow::anyhow!("{}: {}", path.display(), e)))
+        .collect()
+}
+
 pub struct CanvasClient {
     cfg: CanvasCfg,
-    client: reqwest::blocking::Client,
+    http: Http,
     _verbose: bool,
+    audit: Option<AuditLog>,
 }
 
 impl CanvasClient {
-    pub fn new(cfg: CanvasCfg, verbose: bool) -> anyhow::Result<Self> {
-        let client = reqwest::blocking::Client::builder().user_agent("autograder-rust/0.1").build()?;
-        Ok(CanvasClient { cfg, client, _verbose: verbose })
+    pub fn with_http_opts(cfg: CanvasCfg, verbose: bool, opts: HttpOpts) -> anyhow::Result<Self> {
+        let http = Http::new(Self::auth_headers(&cfg), opts)?;
+        Ok(CanvasClient { cfg, http, _verbose: verbose, audit: None })
+    }
+
+    // Log every grade written from now on under a new run id, which is returned.
+    // The log is opened here so an unwritable path fails before anything is uploaded.
+    pub fn set_audit_log(&mut self, path: &Path, undo_of: Option<&str>) -> anyhow::Result<String> {
+        if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) { fs::create_dir_all(dir)?; }
+        OpenOptions::new().create(true).append(true).open(path).map_err(|e| anyhow::anyhow!("{}: {}", path.display(), e))?;
+        // Runs in the same second get a numeric suffix so ids stay unique within the log
+        let stamp = chrono::Local::now().format("%Y%m%d-%H%M%S").to_string();
+        let used: std::collections::HashSet<String> = read_audit_log(path).unwrap_or_default().into_iter().map(|e| e.run_id).collect();
+        let run_id = (1..).map(|n| if n == 1 { stamp.clone() } else { format!("{}-{}", stamp, n) }).find(|id| !used.contains(id)).unwrap_or(stamp);
+        self.audit = Some(AuditLog { path: path.to_path_buf(), run_id: run_id.clone(), undo_of: undo_of.map(String::from) });
+        Ok(run_id)
     }
 
     fn base(&self) -> String {
         if self.cfg.host_name.contains("://") { self.cfg.host_name.clone() } else { format!("https://{}", self.cfg.host_name) }
     }
 
-    fn auth_headers(&self) -> reqwest::header::HeaderMap {
+    fn auth_headers(cfg: &CanvasCfg) -> reqwest::header::HeaderMap {
         use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION};
         let mut headers = HeaderMap::new();
-        let token = format!("Bearer {}", self.cfg.access_token);
+        let token = format!("Bearer {}", cfg.access_token);
         headers.insert(AUTHORIZATION, HeaderValue::from_str(&token).unwrap_or(HeaderValue::from_static("")));
         headers
     }
@@ -65,7 +113,7 @@ impl CanvasClient {
     pub fn get_course_id(&self) -> anyhow::Result<i64> {
         let mut url = self.url("api/v1/courses?per_page=100");
         loop {
-            let res = self.client.get(&url).headers(self.auth_headers()).send()?;
+            let res = self.http.get(&url)?;
             if !res.status().is_success() { anyhow::bail!("courses GET failed: {}", res.status()); }
             let link_header = res.headers().get("Link").cloned();
             let courses: serde_json::Value = res.json()?;
@@ -82,7 +130,7 @@ impl CanvasClient {
     pub fn get_assignment_id(&self, course_id: i64, assignment_name: &str) -> anyhow::Result<i64> {
         let mut url = self.url(&format!("api/v1/courses/{}/assignments?per_page=50", course_id));
         loop {
-            let res = self.client.get(&url).headers(self.auth_headers()).send()?;
+            let res = self.http.get(&url)?;
             if !res.status().is_success() { anyhow::bail!("assignments GET failed: {}", res.status()); }
             let link_header = res.headers().get("Link").cloned();
             let assigns: serde_json::Value = res.json()?;
@@ -98,7 +146,7 @@ impl CanvasClient {
 
     pub fn get_enrollment(&self, course_id: i64) -> anyhow::Result<Vec<Enrollment>> {
         let url = self.url(&format!("api/v1/courses/{}/enrollments?per_page=50", course_id));
-        let res = self.client.get(url).headers(self.auth_headers()).send()?;
+        let res = self.http.get(&url)?;
         if !res.status().is_success() { anyhow::bail!("enrollments GET failed: {}", res.status()); }
         let list: Vec<Enrollment> = res.json()?;
         Ok(list)
@@ -106,20 +154,42 @@ impl CanvasClient {
 
     pub fn get_submission_score(&self, course_id: i64, assignment_id: i64, user_id: i64) -> anyhow::Result<Option<f64>> {
         let url = self.url(&format!("api/v1/courses/{}/assignments/{}/submissions/{}", course_id, assignment_id, user_id));
-        let res = self.client.get(url).headers(self.auth_headers()).send()?;
+        let res = self.http.get(&url)?;
         if !res.status().is_success() { anyhow::bail!("submission GET failed: {}", res.status()); }
         let v: serde_json::Value = res.json()?;
         Ok(v.get("score").and_then(|s| s.as_f64()))
     }
 
-    pub fn put_submission(&self, course_id: i64, assignment_id: i64, user_id: i64, score: i64, comment: &str) -> anyhow::Result<bool> {
+    // old is the score read before the upload; it is what `upload --undo` restores
+    pub fn put_submission(&self, course_id: i64, assignment_id: i64, user_id: i64, old: Option<f64>, score: i64, comment: &str) -> anyhow::Result<bool> {
+        self.put_grade(course_id, assignment_id, user_id, old, Some(score as f64), Some(comment))
+    }
+
+    // An empty posted_grade removes the grade
+    fn put_grade(&self, course_id: i64, assignment_id: i64, user_id: i64, old: Option<f64>, new: Option<f64>, comment: Option<&str>) -> anyhow::Result<bool> {
         let url = self.url(&format!("api/v1/courses/{}/assignments/{}/submissions/{}", course_id, assignment_id, user_id));
-        let params = [
-            ("submission[posted_grade]", score.to_string()),
-            ("comment[text_comment]", comment.to_string()),
-        ];
-        let res = self.client.put(url).headers(self.auth_headers()).form(&params).send()?;
-        Ok(res.status().is_success())
+        let mut params = vec![("submission[posted_grade]", new.map(|n| n.to_string()).unwrap_or_default())];
+        if let Some(c) = comment { params.push(("comment[text_comment]", c.to_string())); }
+        let ok = self.http.put_form(&url, &params)?.status().is_success();
+        if let Some(log) = &self.audit {
+            let entry = AuditEntry {
+                run_id: log.run_id.clone(),
+                timestamp: chrono::Local::now().to_rfc3339(),
+                course_id,
+                course: self.cfg.course_name.clone(),
+                assignment_id,
+                user_id,
+                old_score: old,
+                new_score: new,
+                comment_sha256: comment.map(|c| Sha256::digest(c.as_bytes()).iter().map(|b| format!("{:02x}", b)).collect()),
+                ok,
+                undo_of: log.undo_of.clone(),
+            };
+            let appended = OpenOptions::new().create(true).append(true).open(&log.path)
+                .and_then(|mut f| writeln!(f, "{}", serde_json::to_string(&entry).unwrap_or_default()));
+            if let Err(e) = appended { print_red(&format!("Audit log {}: {}\n", log.path.display(), e)); }
+        }
+        Ok(ok)
     }
 }
 
@@ -128,11 +198,11 @@ pub struct Enrollment { user: EnrollmentUser, user_id: i64 }
 #[derive(Debug, Deserialize)]
 struct EnrollmentUser { login_id: String }
 
-fn next_link_from_header(link: Option<&reqwest::header::HeaderValue>) -> Option<String> {
+pub(crate) fn next_link_from_header(link: Option<&reqwest::header::HeaderValue>) -> Option<String> {
     let val = link?.to_str().ok()?;
     for part in val.split(',') {
         let part = part.trim();
-        if part.contains("rel=next") {
+        if part.contains("rel=next") || part.contains("rel=\"next\"") {
             let start = part.find('<')? + 1;
             let end = part.find('>')?;
             return Some(part[start..end].to_string());
@@ -148,8 +218,116 @@ struct ClassResultItem {
     comment: String,
 }
 
-pub fn upload_class(canvas: CanvasCfg, mapper_cfg: CanvasMapperCfg, project: &str, file: Option<&str>, verbose: bool, by_date: bool) -> anyhow::Result<()> {
-    let json_path = if by_date && file.is_none() {
+#[derive(Debug, Clone, Default)]
+pub struct UploadOpts {
+    pub verbose: bool,
+    // Where to log every grade written; None disables the log
+    pub audit_log: Option<PathBuf>,
+    // Choose the JSON interactively from the current directory
+    pub by_date: bool,
+    // Print the planned changes without writing to Canvas
+    pub dry_run: bool,
+    // Print the planned changes and ask before writing
+    pub confirm: bool,
+    // Retries and concurrency for the Canvas requests
+    pub http: HttpOpts,
+}
+
+// What an upload does with one result
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UploadAction { Skip, Update, Unmapped, NotEnrolled, LookupFailed }
+
+impl UploadAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            UploadAction::Skip => "skip",
+            UploadAction::Update => "update",
+            UploadAction::Unmapped => "unmapped",
+            UploadAction::NotEnrolled => "not-enrolled",
+            UploadAction::LookupFailed => "lookup-failed",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PlannedUpload {
+    pub student: String,
+    pub login: Option<String>,
+    pub user_id: Option<i64>,
+    // Score in Canvas now, if any
+    pub current: Option<f64>,
+    pub score: i64,
+    pub comment: String,
+    pub action: UploadAction,
+}
+
+// Map each result to a Canvas user and compare with the score already there
+fn plan_upload(client: &CanvasClient, mapper: &CanvasMapper, id_map: &HashMap<String, i64>, course_id: i64, assignment_id: i64, items: Vec<ClassResultItem>, verbose: bool) -> Vec<PlannedUpload> {
+    let mut plan = vec![];
+    for it in items {
+        let Some(student) = it.student else { continue };
+        let mut p = PlannedUpload { student, login: None, user_id: None, current: None, score: it.score, comment: it.comment, action: UploadAction::Unmapped };
+        p.login = mapper.lookup(&p.student);
+        p.user_id = p.login.as_ref().and_then(|l| id_map.get(l).copied());
+        if let (Some(login), Some(user_id)) = (&p.login, p.user_id) {
+            if verbose { println!("Map: {} -> {} (user_id {})", p.student, login, user_id); }
+            // Without the current score there is nothing safe to log for an undo, so the grade is left alone
+            match client.get_submission_score(course_id, assignment_id, user_id) {
+                Ok(current) => {
+                    p.current = current;
+                    let same = p.current.map(|cur| (cur - (p.score as f64)).abs() < f64::EPSILON).unwrap_or(false);
+                    p.action = if same { UploadAction::Skip } else { UploadAction::Update };
+                }
+                Err(e) => {
+                    print_red(&format!("{}: {}\n", login, e));
+                    p.action = UploadAction::LookupFailed;
+                }
+            }
+        } else if p.login.is_some() {
+            p.action = UploadAction::NotEnrolled;
+        }
+        plan.push(p);
+    }
+    plan
+}
+
+pub fn print_plan(plan: &[PlannedUpload]) {
+    let width = |f: &dyn Fn(&PlannedUpload) -> usize, title: &str| plan.iter().map(f).max().unwrap_or(0).max(title.len()) + 2;
+    let sw = width(&|p| p.student.len(), "Student");
+    let lw = width(&|p| p.login.as_deref().map(|l| l.len()).unwrap_or(1), "Login");
+    print_justified("Student", sw);
+    print_justified("Login", lw);
+    println!("{:>8} {:>8}  Action", "Current", "New");
+    for p in plan {
+        print_justified(&p.student, sw);
+        print_justified(p.login.as_deref().unwrap_or("-"), lw);
+        let current = p.current.map(|c| c.to_string()).unwrap_or_else(|| String::from("-"));
+        let line = format!("{:>8} {:>8}  {}\n", current, p.score, p.action.as_str());
+        match p.action {
+            UploadAction::Update => print_green(&line),
+            UploadAction::Skip => print!("{}", line),
+            UploadAction::Unmapped | UploadAction::NotEnrolled | UploadAction::LookupFailed => print_red(&line),
+        }
+    }
+    let count = |a: UploadAction| plan.iter().filter(|p| p.action == a).count();
+    let failed = count(UploadAction::LookupFailed);
+    println!("{} to update, {} unchanged, {} unmapped, {} not enrolled{}",
+        count(UploadAction::Update), count(UploadAction::Skip), count(UploadAction::Unmapped), count(UploadAction::NotEnrolled),
+        if failed > 0 { format!(", {} lookup failed", failed) } else { String::new() });
+}
+
+// Reads a line from stdin; only y or yes counts as agreement
+fn ask_yes(prompt: &str) -> bool {
+    print!("{}", prompt);
+    let _ = std::io::stdout().flush();
+    let mut line = String::new();
+    if std::io::stdin().read_line(&mut line).is_err() { return false; }
+    matches!(line.trim().to_lowercase().as_str(), "y" | "yes")
+}
+
+pub fn upload_class(canvas: CanvasCfg, mapper_cfg: CanvasMapperCfg, project: &str, file: Option<&str>, opts: &UploadOpts) -> anyhow::Result<()> {
+    let verbose = opts.verbose;
+    let json_path = if opts.by_date && file.is_none() {
         // Present arrow-key menu of *.json in cwd; abort on cancel
         let mut files: Vec<String> = fs::read_dir(".")?
             .filter_map(|e| e.ok())
@@ -179,7 +357,8 @@ pub fn upload_class(canvas: CanvasCfg, mapper_cfg: CanvasMapperCfg, project: &st
     if verbose { println!("Uploading from {} ({} results)", json_path, items.len()); }
 
     let mapper = CanvasMapper::from_cfg(&mapper_cfg)?;
-    let client = CanvasClient::new(canvas, verbose)?;
+    let course_name = canvas.course_name.clone();
+    let mut client = CanvasClient::with_http_opts(canvas, verbose, opts.http.clone())?;
     let course_id = client.get_course_id()?;
     let assignment_id = client.get_assignment_id(course_id, project)?;
     if verbose { println!("Course ID: {}, Assignment ID: {}", course_id, assignment_id); }
@@ -189,23 +368,118 @@ pub fn upload_class(canvas: CanvasCfg, mapper_cfg: CanvasMapperCfg, project: &st
     let mut id_map: HashMap<String, i64> = HashMap::new();
     for e in enrollment { id_map.insert(e.user.login_id, e.user_id); }
 
-    for it in items {
-        let Some(student) = it.student else { continue };
-        let Some(login_id) = mapper.lookup(&student) else { print_red(&format!("no mapping for {}\n", student)); continue; };
-        let Some(user_id) = id_map.get(&login_id).copied() else { print_red(&format!("{} not enrolled\n", login_id)); continue; };
-        if verbose { println!("Map: {} -> {} (user_id {})", student, login_id, user_id); }
-        print!("Uploading {} {} ", login_id, it.score);
-        // Skip if same as Canvas
-        if let Ok(Some(cur)) = client.get_submission_score(course_id, assignment_id, user_id) {
-            if (cur - (it.score as f64)).abs() < f64::EPSILON { println!("skipping: new score == score in Canvas"); continue; }
-            if verbose { println!("(current Canvas score: {})", cur); }
+    let plan = plan_upload(&client, &mapper, &id_map, course_id, assignment_id, items, verbose);
+    if opts.dry_run || opts.confirm {
+        println!("{} -> {} / {}", json_path, course_name, project);
+        print_plan(&plan);
+    }
+    if opts.dry_run { print_yellow("Dry run: nothing was written to Canvas\n"); return Ok(()); }
+    let updates = plan.iter().filter(|p| p.action == UploadAction::Update).count();
+    if opts.confirm && !ask_yes(&format!("Upload {} score(s) to {} / {}? [y/N] ", updates, course_name, project)) {
+        print_yellow("Upload cancelled\n");
+        return Ok(());
+    }
+    let run_id = match &opts.audit_log {
+        Some(path) if updates > 0 => Some(client.set_audit_log(path, None)?),
+        _ => None,
+    };
+
+    for p in plan {
+        let login = p.login.as_deref().unwrap_or_default();
+        match (p.action, p.user_id) {
+            (UploadAction::Unmapped, _) => print_red(&format!("no mapping for {}\n", p.student)),
+            (UploadAction::NotEnrolled, _) => print_red(&format!("{} not enrolled\n", login)),
+            (UploadAction::LookupFailed, _) => print_red(&format!("{} skipped: could not read the score in Canvas\n", login)),
+            (UploadAction::Skip, _) => println!("Uploading {} {} skipping: new score == score in Canvas", login, p.score),
+            (UploadAction::Update, Some(user_id)) => {
+                print!("Uploading {} {} ", login, p.score);
+                if verbose { if let Some(cur) = p.current { println!("(current Canvas score: {})", cur); } }
+                match client.put_submission(course_id, assignment_id, user_id, p.current, p.score, &p.comment) {
+                    Ok(true) => print_green("ok\n"),
+                    _ => print_red("failed\n"),
+                }
+            }
+            (UploadAction::Update, None) => {}
         }
-        match client.put_submission(course_id, assignment_id, user_id, it.score, &it.comment) {
-            Ok(true) => print_green("ok\n"),
-            _ => print_red("failed\n"),
+    }
+    if let (Some(run_id), Some(path)) = (run_id, &opts.audit_log) {
+        println!("Logged as upload {} in {}; undo with: grade-rs upload --undo {}", run_id, path.display(), run_id);
+    }
+
+    Ok(())
+}
+
+// What undoing one submission does
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum UndoAction { Restore, Unchanged, Changed }
+
+// One submission written by an upload run
+struct UndoTarget {
+    course_id: i64,
+    assignment_id: i64,
+    user_id: i64,
+    // Score before the run, and what the run left
+    old: Option<f64>,
+    new: Option<f64>,
+}
+
+// Put back the scores an upload run replaced. Submissions changed in Canvas since then are left alone.
+pub fn undo_upload(canvas: CanvasCfg, run_id: &str, opts: &UploadOpts) -> anyhow::Result<()> {
+    let Some(path) = &opts.audit_log else { anyhow::bail!("the upload log is disabled ([Config] upload_log)") };
+    let entries: Vec<AuditEntry> = read_audit_log(path)?.into_iter().filter(|e| e.run_id == run_id && e.ok).collect();
+    if entries.is_empty() { anyhow::bail!("no uploads recorded for run {} in {}", run_id, path.display()); }
+    // The first write in the run holds the score from before it, the last what the run left behind
+    let mut targets: Vec<UndoTarget> = vec![];
+    for e in &entries {
+        match targets.iter_mut().find(|t| (t.course_id, t.assignment_id, t.user_id) == (e.course_id, e.assignment_id, e.user_id)) {
+            Some(t) => t.new = e.new_score,
+            None => targets.push(UndoTarget { course_id: e.course_id, assignment_id: e.assignment_id, user_id: e.user_id, old: e.old_score, new: e.new_score }),
         }
     }
+    let course = entries[0].course.clone();
+    let mut client = CanvasClient::with_http_opts(canvas, opts.verbose, opts.http.clone())?;
+    let same = |a: Option<f64>, b: Option<f64>| match (a, b) {
+        (Some(a), Some(b)) => (a - b).abs() < f64::EPSILON,
+        (None, None) => true,
+        _ => false,
+    };
+    let fmt = |s: Option<f64>| s.map(|v| v.to_string()).unwrap_or_else(|| String::from("-"));
+    let mut plan = vec![];
+    for t in targets {
+        let current = client.get_submission_score(t.course_id, t.assignment_id, t.user_id)?;
+        let action = if same(current, t.old) { UndoAction::Unchanged } else if same(current, t.new) { UndoAction::Restore } else { UndoAction::Changed };
+        plan.push((t, current, action));
+    }
 
+    println!("Undo upload {} to {} ({} submissions)", run_id, course, plan.len());
+    println!("{:>10} {:>10} {:>8} {:>8}  Action", "Assignment", "User", "Current", "Restore");
+    for (t, current, action) in &plan {
+        let line = format!("{:>10} {:>10} {:>8} {:>8}  ", t.assignment_id, t.user_id, fmt(*current), fmt(t.old));
+        match action {
+            UndoAction::Restore => print_green(&format!("{}restore\n", line)),
+            UndoAction::Unchanged => println!("{}skip", line),
+            UndoAction::Changed => print_red(&format!("{}changed since upload\n", line)),
+        }
+    }
+    let restores = plan.iter().filter(|p| p.2 == UndoAction::Restore).count();
+    if opts.dry_run { print_yellow("Dry run: nothing was written to Canvas\n"); return Ok(()); }
+    if restores == 0 { return Ok(()); }
+    if opts.confirm && !ask_yes(&format!("Restore {} score(s) in {}? [y/N] ", restores, course)) {
+        print_yellow("Undo cancelled\n");
+        return Ok(());
+    }
+    let undo_run = client.set_audit_log(path, Some(run_id))?;
+    let mut failed = 0;
+    for (t, current, action) in plan {
+        if action != UndoAction::Restore { continue; }
+        // Comments stay; Canvas has no way to remove them through this endpoint
+        match client.put_grade(t.course_id, t.assignment_id, t.user_id, current, t.old, None) {
+            Ok(true) => {}
+            _ => { failed += 1; print_red(&format!("Restoring user {} failed\n", t.user_id)); }
+        }
+    }
+    print_green(&format!("Restored {} score(s); logged as {}\n", restores - failed, undo_run));
+    if failed > 0 { anyhow::bail!("{} restore(s) failed", failed); }
     Ok(())
 }
 
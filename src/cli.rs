@@ -24,6 +24,10 @@ pub enum Commands {
         quiet: bool,
         #[arg(long = "no-color", help = "Disable ANSI color output")]
         no_color: bool,
+        #[arg(long = "artifact", help = "Also write results in the Actions artifact format, e.g. grade-results.json")]
+        artifact: Option<String>,
+        #[arg(long = "check-run", help = "In GitHub Actions, publish the results as a check run on the commit")]
+        check_run: bool,
     },
     Class {
         #[arg(short = 'p', long = "project")]
@@ -40,12 +44,30 @@ pub enum Commands {
         students: Option<Vec<String>>,
         #[arg(short = 'd', long = "by-date", help = "Select a date from dates.toml for suffix and checkout context")]
         by_date: bool,
-        #[arg(short = 'j', long = "jobs", help = "Number of parallel jobs (default: CPUs)")]
+        #[arg(short = 'j', long = "jobs", help = "Number of parallel jobs (default: CPUs, or 8 with -g)")]
         jobs: Option<usize>,
         #[arg(long = "quiet", help = "Suppress per-test-case pass/fail lines")]
         quiet: bool,
         #[arg(long = "no-color", help = "Disable ANSI color output")]
         no_color: bool,
+        #[arg(long = "rerun-failures", help = "Re-execute only the failed tests recorded in the previous class JSON")]
+        rerun_failures: bool,
+        #[arg(long = "incremental", help = "Reuse cached results for repos whose HEAD and tests are unchanged")]
+        incremental: bool,
+        #[arg(long = "artifact-name", help = "With -g, only use artifacts with this name")]
+        artifact_name: Option<String>,
+        #[arg(long = "workflow", help = "With -g, only use artifacts from this workflow (name or file)")]
+        workflow: Option<String>,
+        #[arg(long = "branch", help = "With -g, only use artifacts from runs on this branch")]
+        branch: Option<String>,
+        #[arg(long = "rerun", num_args = 0..=1, default_missing_value = "all", requires = "github_action", conflicts_with = "by_date", help = "With -g, first re-run each workflow (all, failed or dispatch) and grade the new run")]
+        rerun: Option<String>,
+        #[arg(long = "timeout", default_value_t = 1800, help = "With --rerun, seconds to wait for runs to finish")]
+        timeout: u64,
+        #[arg(long = "tag", help = "Check out this submission tag (default: [project] submission_tag)")]
+        tag: Option<String>,
+        #[arg(long = "tag-fallback", help = "When the tag is missing: deadline or not-submitted")]
+        tag_fallback: Option<String>,
     },
     Exec {
         #[arg(short = 'p', long = "project")]
@@ -70,12 +92,38 @@ pub enum Commands {
         date: Option<String>,
         #[arg(short = 'd', long = "by-date", help = "Select a date from dates.toml for suffix and checkout context")] 
         by_date: bool,
+        #[arg(short = 'j', long = "jobs", help = "Number of parallel jobs (default: CPUs)")]
+        jobs: Option<usize>,
+        #[arg(long = "tag", help = "Check out this submission tag (default: [project] submission_tag)")]
+        tag: Option<String>,
+        #[arg(long = "tag-fallback", help = "When the tag is missing: deadline or not-submitted")]
+        tag_fallback: Option<String>,
     },
     Pull {
         #[arg(short = 'p', long = "project")]
         project: Option<String>,
         #[arg(short = 's', long = "students")]
         students: Option<Vec<String>>,
+        #[arg(short = 'j', long = "jobs", help = "Number of parallel jobs (default: CPUs)")]
+        jobs: Option<usize>,
+        #[arg(long = "tag", help = "Check out this submission tag (default: [project] submission_tag)")]
+        tag: Option<String>,
+        #[arg(long = "tag-fallback", help = "When the tag is missing: deadline or not-submitted")]
+        tag_fallback: Option<String>,
+    },
+    Checkout {
+        #[arg(short = 'p', long = "project")]
+        project: Option<String>,
+        #[arg(short = 's', long = "students")]
+        students: Option<Vec<String>>,
+        #[arg(long = "date", help = "Move to the last commit before this 'YYYY-MM-DD[ HH:MM:SS]' date")]
+        date: Option<String>,
+        #[arg(short = 'd', long = "by-date", help = "Select a date from dates.toml")]
+        by_date: bool,
+        #[arg(long = "branch", help = "Branch to follow (default: the remote's default branch)")]
+        branch: Option<String>,
+        #[arg(short = 'j', long = "jobs", help = "Number of parallel jobs (default: CPUs)")]
+        jobs: Option<usize>,
     },
     Upload {
         #[arg(short = 'p', long = "project")]
@@ -86,6 +134,87 @@ pub enum Commands {
         verbose: bool,
         #[arg(short = 'd', long = "by-date", help = "Select JSON from current dir (arrow keys)")]
         by_date: bool,
+        #[arg(long = "dry-run", help = "Show current and new scores for each student without writing to Canvas")]
+        dry_run: bool,
+        #[arg(long = "confirm", conflicts_with = "dry_run", help = "Show the changes and ask before uploading")]
+        confirm: bool,
+        #[arg(long = "undo", value_name = "RUN_ID", conflicts_with_all = ["file", "by_date"], help = "Restore the scores an earlier upload replaced, using the upload log")]
+        undo: Option<String>,
+    },
+    History {
+        #[arg(short = 'p', long = "project")]
+        project: Option<String>,
+        #[arg(short = 's', long = "students")]
+        students: Option<Vec<String>>,
+        #[arg(short = 'd', long = "by-date", help = "Select a date from dates.toml for the repo suffix")]
+        by_date: bool,
+    },
+    Audit {
+        #[arg(short = 'p', long = "project")]
+        project: Option<String>,
+        #[arg(short = 's', long = "students")]
+        students: Option<Vec<String>>,
+        #[arg(long = "date", help = "Deadline 'YYYY-MM-DD[ HH:MM:SS]' for the large-commit check")]
+        date: Option<String>,
+        #[arg(short = 'd', long = "by-date", help = "Select the deadline and repo suffix from dates.toml")]
+        by_date: bool,
+        #[arg(long = "allow", help = "Author names or emails always accepted (e.g. the starter code author)")]
+        allow: Vec<String>,
+        #[arg(long = "large-lines", default_value_t = 500, help = "Lines changed that make a commit large")]
+        large_lines: u64,
+        #[arg(long = "window-hours", default_value_t = 24.0, help = "Hours before the deadline in which large commits are flagged")]
+        window_hours: f64,
+    },
+    Roster {
+        #[command(subcommand)]
+        action: RosterCmd,
+    },
+    Distribute {
+        #[arg(short = 'p', long = "project")]
+        project: Option<String>,
+        #[arg(long = "files", num_args = 1.., required = true, help = "Files or directories to copy into each repo")]
+        files: Vec<String>,
+        #[arg(long = "dest", help = "Directory in the repo to place them in (default: the same relative path)")]
+        dest: Option<String>,
+        #[arg(long = "branch", help = "Push to this branch instead of the default branch")]
+        branch: Option<String>,
+        #[arg(short = 'm', long = "message", default_value = "Update course files")]
+        message: String,
+        #[arg(long = "pr", requires = "branch", help = "Open a pull request from --branch into the default branch")]
+        pr: bool,
+        #[arg(long = "title", help = "Pull request title (default: the commit message)")]
+        title: Option<String>,
+        #[arg(short = 's', long = "students")]
+        students: Option<Vec<String>>,
+        #[arg(short = 'j', long = "jobs", help = "Number of parallel jobs (default: CPUs)")]
+        jobs: Option<usize>,
+        #[arg(short = 'd', long = "by-date", help = "Select a date from dates.toml for the repo suffix")]
+        by_date: bool,
+    },
+    Workflow {
+        #[command(subcommand)]
+        action: WorkflowCmd,
+    },
+    Feedback {
+        #[command(subcommand)]
+        action: FeedbackCmd,
+    },
+    Actions {
+        #[command(subcommand)]
+        action: ActionsCmd,
+    },
+    #[command(args_conflicts_with_subcommands = true)]
+    Archive {
+        #[command(subcommand)]
+        action: Option<ArchiveCmd>,
+        #[arg(short = 'p', long = "project")]
+        project: Option<String>,
+        #[arg(short = 's', long = "students")]
+        students: Option<Vec<String>>,
+        #[arg(short = 'd', long = "by-date", help = "Select a date from dates.toml for the repo suffix")]
+        by_date: bool,
+        #[arg(short = 'o', long = "output", help = "Zip file to write (default: <project>[-<suffix>]-archive.zip)")]
+        output: Option<String>,
     },
     Rollup {
         #[arg(short = 'p', long = "project")]
@@ -108,3 +237,89 @@ pub enum Commands {
 impl Cli {
     pub fn parse() -> Self { <Cli as Parser>::parse() }
 }
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum RosterCmd {
+    // Merge students from the org's <project>-* repos into the roster file
+    Sync {
+        #[arg(short = 'p', long = "project")]
+        project: Option<String>,
+        #[arg(long = "roster", help = "Roster file to update (default: Config.roster or roster.txt)")]
+        roster: Option<String>,
+        #[arg(long = "exclude", help = "Repo suffixes that are not students, e.g. starter or solution")]
+        exclude: Vec<String>,
+        #[arg(long = "check", help = "Report differences without writing the roster")]
+        check: bool,
+    },
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum WorkflowCmd {
+    // Write a GitHub Actions workflow that runs `grade-rs test` and uploads the artifact
+    Generate {
+        #[arg(short = 'p', long = "project")]
+        project: Option<String>,
+        #[arg(short = 'o', long = "output", default_value = ".github/workflows/grade.yml", help = "Workflow file to write, or - for stdout")]
+        output: String,
+        #[arg(long = "tests-repo", help = "owner/name of the tests repo (default: <Git.org>/tests)")]
+        tests_repo: Option<String>,
+        #[arg(long = "install-from", help = "Git URL to cargo install grade-rs from (default: https://<Git.host>/<Git.org>/autograder-rust)")]
+        install_from: Option<String>,
+        #[arg(long = "artifact-name", default_value = "grade", help = "Name of the uploaded artifact")]
+        artifact_name: String,
+        #[arg(long = "check-run", help = "Also publish a check run with annotations on each commit")]
+        check_run: bool,
+        #[arg(long = "force", help = "Overwrite an existing workflow file")]
+        force: bool,
+    },
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum FeedbackCmd {
+    // Post each student's results to their GitHub repo
+    Github {
+        #[arg(short = 'p', long = "project")]
+        project: Option<String>,
+        #[arg(long = "mode", default_value = "issue", help = "issue, commit-comment or pr")]
+        mode: String,
+        #[arg(long = "file", help = "Class results JSON (default: <project>.json)")]
+        file: Option<String>,
+        #[arg(short = 'd', long = "by-date", help = "Select a date from dates.toml for the JSON and repo suffix")]
+        by_date: bool,
+        #[arg(short = 's', long = "students")]
+        students: Option<Vec<String>>,
+        #[arg(long = "dry-run", help = "Show what would be posted without writing to GitHub")]
+        dry_run: bool,
+    },
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum ActionsCmd {
+    // Re-run or dispatch each student's workflow, optionally waiting for the runs to finish
+    Rerun {
+        #[arg(short = 'p', long = "project")]
+        project: Option<String>,
+        #[arg(short = 's', long = "students")]
+        students: Option<Vec<String>>,
+        #[arg(long = "mode", default_value = "all", help = "all, failed or dispatch")]
+        mode: String,
+        #[arg(long = "workflow", help = "Workflow name or file; dispatch needs the file name (default: grade.yml)")]
+        workflow: Option<String>,
+        #[arg(long = "branch", help = "Re-run the latest run on this branch, or dispatch on it (default: the default branch)")]
+        branch: Option<String>,
+        #[arg(long = "wait", help = "Wait for the runs to finish and report their conclusions")]
+        wait: bool,
+        #[arg(long = "timeout", default_value_t = 1800, help = "With --wait, seconds to wait for runs to finish")]
+        timeout: u64,
+        #[arg(short = 'j', long = "jobs", help = "Number of parallel jobs (default: 8)")]
+        jobs: Option<usize>,
+    },
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum ArchiveCmd {
+    // Recheck an archive's files against its manifest
+    Verify {
+        file: String,
+    },
+}
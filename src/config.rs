@@ -19,6 +19,35 @@ fn default_digital_path() -> String { String::from("~/Digital/Digital.jar") }
 pub struct ConfigCfg {
     #[serde(default)]
     pub students: Vec<String>,
+    // Written by `roster sync`; used when students is empty
+    #[serde(default)]
+    pub roster: Option<String>,
+    // ETag cache for GitHub API responses; "" turns it off
+    #[serde(default)]
+    pub http_cache: Option<String>,
+    // JSONL log of every grade written to Canvas; "" turns it off
+    #[serde(default)]
+    pub upload_log: Option<String>,
+}
+
+impl ConfigCfg {
+    pub fn roster_path(&self) -> String { expand_tilde(self.roster.as_deref().unwrap_or("roster.txt")) }
+
+    pub fn http_cache_dir(&self) -> Option<PathBuf> {
+        let dir = self.http_cache.as_deref().unwrap_or("~/.cache/grade/http");
+        if dir.is_empty() { None } else { Some(PathBuf::from(expand_tilde(dir))) }
+    }
+
+    pub fn upload_log_path(&self) -> Option<PathBuf> {
+        let path = self.upload_log.as_deref().unwrap_or("~/.local/share/grade/canvas-uploads.jsonl");
+        if path.is_empty() { None } else { Some(PathBuf::from(expand_tilde(path))) }
+    }
+
+    // Config.students, or the roster file when that list is empty
+    pub fn student_list(&self) -> Vec<String> {
+        if !self.students.is_empty() { return self.students.clone(); }
+        crate::roster::read_roster(&self.roster_path()).unwrap_or_default()
+    }
 }
 
 #[derive(Debug, Deserialize, Clone, Default)]
@@ -27,8 +56,17 @@ pub struct GitCfg {
     pub org: String,
     #[serde(default = "default_credentials")]
     pub credentials: String,
+    #[serde(default = "default_git_host")]
+    pub host: String,
+    // Clone URL with {host}, {org}, {project}, {student} placeholders; overrides credentials
+    #[serde(default)]
+    pub url_template: Option<String>,
+    // Browser link used in comments; also accepts {commit}
+    #[serde(default)]
+    pub web_url_template: Option<String>,
 }
 fn default_credentials() -> String { String::from("ssh") }
+fn default_git_host() -> String { String::from("github.com") }
 
 #[derive(Debug, Deserialize, Clone, Default)]
 pub struct GithubCfg {
@@ -117,10 +155,16 @@ impl Config {
 
 [Config]
 # students = []
+# roster = "roster.txt" # read when students is empty; written by `grade-rs roster sync`
+# http_cache = "~/.cache/grade/http" # GitHub API ETag cache; "" disables
+# upload_log = "~/.local/share/grade/canvas-uploads.jsonl" # audit log of Canvas grade changes; "" disables
 
 [Git]
 # org = "your-org"
 # credentials = "ssh" # or "https"
+# host = "github.com"
+# url_template = "git@{host}:{org}/{project}-{student}.git" # or "file:///srv/git/{project}-{student}.git"
+# web_url_template = "https://{host}/{org}/{project}-{student}/tree/{commit}"
 
 [Github]
 # host_name = "api.github.com"
@@ -1,4 +1,6 @@
+use chrono::{Local, NaiveDateTime, TimeZone};
 use serde::Deserialize;
+use std::collections::HashMap;
 use std::fs;
 use std::path::Path;
 
@@ -12,12 +14,51 @@ pub struct DateItem {
 }
 
 #[derive(Debug, Deserialize, Clone)]
-pub struct DatesTable { pub dates: Vec<DateItem> }
+pub struct DatesTable {
+    pub dates: Vec<DateItem>,
+    #[serde(default)] pub late_policy: Option<LatePolicy>,
+}
+
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct LatePolicy {
+    #[serde(default)] pub percent_per_day: f64,
+    #[serde(default)] pub grace_minutes: u64,
+    // Days late after which nothing is earned; 0 means no limit
+    #[serde(default)] pub max_days: u32,
+    // Student -> hours added to their deadline
+    #[serde(default)] pub extensions: HashMap<String, f64>,
+}
+
+impl LatePolicy {
+    // Percent to deduct and an explanation line, or None when on time after extension and grace
+    pub fn penalty(&self, student: &str, hours_late: f64) -> Option<(f64, String)> {
+        let ext = self.extensions.get(student).copied().unwrap_or(0.0);
+        let late = hours_late - ext;
+        if late * 60.0 <= self.grace_minutes as f64 { return None; }
+        let days = (late / 24.0).ceil().max(1.0) as u32;
+        let ext_note = if ext > 0.0 { format!(" after {}h extension", ext) } else { String::new() };
+        if self.max_days > 0 && days > self.max_days {
+            return Some((100.0, format!("Late {:.1} hours{} ({} days, over the {} day limit): no credit", late, ext_note, days, self.max_days)));
+        }
+        let percent = (days as f64 * self.percent_per_day).min(100.0);
+        Some((percent, format!("Late {:.1} hours{} ({} day(s) at {}% per day): -{}%", late, ext_note, days, self.percent_per_day, percent)))
+    }
+}
+
+// Seconds since the epoch for a 'YYYY-MM-DD[ HH:MM:SS]' local time; date-only means midnight, as with git --before
+pub fn parse_deadline(date: &str) -> anyhow::Result<i64> {
+    let full = if date.contains(' ') { date.to_string() } else { format!("{} 00:00:00", date) };
+    let naive = NaiveDateTime::parse_from_str(&full, "%Y-%m-%d %H:%M:%S")
+        .map_err(|e| anyhow::anyhow!("Invalid date \"{}\": {}", date, e))?;
+    Local.from_local_datetime(&naive).earliest()
+        .map(|t| t.timestamp())
+        .ok_or_else(|| anyhow::anyhow!("Date \"{}\" does not exist in the local time zone", date))
+}
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct DatesDoc { #[serde(flatten)] pub projects: std::collections::HashMap<String, DatesTable> }
 
-pub struct Dates { pub items: Vec<DateItem> }
+pub struct Dates { pub items: Vec<DateItem>, pub late_policy: Option<LatePolicy> }
 
 impl Dates {
     pub fn from_tests_path(tests_path: &str, project: &str) -> anyhow::Result<Self> {
@@ -25,7 +66,7 @@ impl Dates {
         let content = fs::read_to_string(&path)?;
         let doc: DatesDoc = toml::from_str(&content)?;
         let table = doc.projects.get(project).ok_or_else(|| anyhow::anyhow!("No dates for project {} in {}", project, path.display()))?;
-        Ok(Dates { items: table.dates.clone() })
+        Ok(Dates { items: table.dates.clone(), late_policy: table.late_policy.clone() })
     }
 
     pub fn select(&self) -> Option<&DateItem> {
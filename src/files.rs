@@ -0,0 +1,133 @@
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::Path;
+
+use crate::testcases::TcResult;
+
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct FilesCfg {
+    #[serde(default)] pub required: Vec<String>,
+    #[serde(default)] pub forbidden: Vec<String>,
+    #[serde(default)] pub forbidden_patterns: Vec<String>,
+    #[serde(default)] pub protected: Vec<String>,
+    // Globs limiting which files are scanned for forbidden_patterns; empty scans every text file
+    #[serde(default)] pub scan: Vec<String>,
+    // Points per rule; 0 reports violations as warnings only
+    #[serde(default)] pub rubric: i64,
+}
+
+impl FilesCfg {
+    pub fn rule_count(&self) -> usize {
+        self.required.len() + self.forbidden.len() + self.forbidden_patterns.len() + self.protected.len()
+    }
+
+    pub fn total_rubric(&self) -> i64 { self.rubric * self.rule_count() as i64 }
+}
+
+// Patterns containing '/' match the path relative to the repo root; others match the file name anywhere
+pub fn glob_matches(pattern: &str, rel: &str) -> bool {
+    let Ok(pat) = glob::Pattern::new(pattern) else { return pattern == rel };
+    let opts = glob::MatchOptions { case_sensitive: true, require_literal_separator: true, require_literal_leading_dot: false };
+    if pattern.contains('/') {
+        pat.matches_with(rel, opts)
+    } else {
+        let name = rel.rsplit('/').next().unwrap_or(rel);
+        pat.matches_with(name, opts)
+    }
+}
+
+// Relative paths ('/' separated) of all files under root, skipping .git
+pub fn list_files(root: &Path) -> Vec<String> {
+    fn walk(dir: &Path, prefix: &str, out: &mut Vec<String>) {
+        let Ok(rd) = fs::read_dir(dir) else { return };
+        let mut entries: Vec<_> = rd.filter_map(|e| e.ok()).collect();
+        entries.sort_by_key(|e| e.file_name());
+        for e in entries {
+            let name = e.file_name().to_string_lossy().to_string();
+            if name == ".git" { continue; }
+            let rel = if prefix.is_empty() { name.clone() } else { format!("{}/{}", prefix, name) };
+            let Ok(ft) = e.file_type() else { continue };
+            if ft.is_dir() { walk(&e.path(), &rel, out); } else if ft.is_file() { out.push(rel); }
+        }
+    }
+    let mut out = vec![];
+    walk(root, "", &mut out);
+    out
+}
+
+pub fn sha256_file(path: &Path) -> std::io::Result<String> {
+    let bytes = fs::read(path)?;
+    let digest = Sha256::digest(&bytes);
+    Ok(digest.iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+fn rule_result(cfg: &FilesCfg, name: String, violation: Option<String>) -> Option<TcResult> {
+    match violation {
+        None if cfg.rubric == 0 => None,
+        None => Some(TcResult { rubric: cfg.rubric, score: cfg.rubric, test: name, test_err: None, ..Default::default() }),
+        Some(msg) => Some(TcResult { rubric: cfg.rubric, score: 0, test: name, test_err: Some(msg), ..Default::default() }),
+    }
+}
+
+fn summarize(paths: &[&String]) -> String {
+    let mut s = paths.iter().take(3).map(|p| p.as_str()).collect::<Vec<_>>().join(", ");
+    if paths.len() > 3 { s.push_str(&format!(" (+{} more)", paths.len() - 3)); }
+    s
+}
+
+// Evaluate each rule against the student repo. Protected files are compared with the
+// same relative path under tests_dir. Passing warning-only rules produce no result.
+// .grade is skipped: the generated GitHub workflow checks the tests repo out there.
+pub fn check_files(cfg: &FilesCfg, repo_root: &Path, tests_dir: &Path) -> anyhow::Result<Vec<TcResult>> {
+    let files: Vec<String> = list_files(repo_root).into_iter().filter(|f| !f.starts_with(".grade/")).collect();
+    let mut results = vec![];
+
+    for pat in &cfg.required {
+        let found = files.iter().any(|f| glob_matches(pat, f));
+        let violation = if found { None } else { Some(format!("Required file missing: {}", pat)) };
+        results.extend(rule_result(cfg, format!("required:{}", pat), violation));
+    }
+
+    for pat in &cfg.forbidden {
+        let hits: Vec<&String> = files.iter().filter(|f| glob_matches(pat, f)).collect();
+        let violation = if hits.is_empty() { None } else { Some(format!("Forbidden file present: {}", summarize(&hits))) };
+        results.extend(rule_result(cfg, format!("forbidden:{}", pat), violation));
+    }
+
+    if !cfg.forbidden_patterns.is_empty() {
+        let mut regexes = vec![];
+        for p in &cfg.forbidden_patterns {
+            let re = regex::Regex::new(p).map_err(|e| anyhow::anyhow!("Invalid forbidden pattern \"{}\": {}", p, e))?;
+            regexes.push(re);
+        }
+        let scanned: Vec<&String> = files.iter().filter(|f| cfg.scan.is_empty() || cfg.scan.iter().any(|g| glob_matches(g, f))).collect();
+        // Skip binary files: only text that decodes as UTF-8 is scanned
+        let contents: Vec<(&String, String)> = scanned.into_iter()
+            .filter_map(|f| fs::read(repo_root.join(f)).ok().and_then(|b| String::from_utf8(b).ok()).map(|s| (f, s)))
+            .collect();
+        for (p, re) in cfg.forbidden_patterns.iter().zip(regexes.iter()) {
+            let mut hit: Option<String> = None;
+            'files: for (f, text) in &contents {
+                for (i, line) in text.lines().enumerate() {
+                    if re.is_match(line) { hit = Some(format!("{}:{}", f, i + 1)); break 'files; }
+                }
+            }
+            let violation = hit.map(|loc| format!("Forbidden pattern \"{}\" found at {}", p, loc));
+            results.extend(rule_result(cfg, format!("pattern:{}", p), violation));
+        }
+    }
+
+    for rel in &cfg.protected {
+        let reference = tests_dir.join(rel);
+        let expected = sha256_file(&reference).map_err(|e| anyhow::anyhow!("Protected file not found in tests repo: {} ({})", reference.display(), e))?;
+        let violation = match sha256_file(&repo_root.join(rel)) {
+            Ok(actual) if actual == expected => None,
+            Ok(_) => Some(format!("Protected file modified: {}", rel)),
+            Err(_) => Some(format!("Protected file missing: {}", rel)),
+        };
+        results.extend(rule_result(cfg, format!("protected:{}", rel), violation));
+    }
+
+    Ok(results)
+}
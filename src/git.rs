@@ -1,9 +1,11 @@
+use serde::Serialize;
+use std::collections::HashMap;
 use std::path::PathBuf;
 use std::process::{Command, Stdio};
 
 use crate::config::GitCfg;
-use crate::testcases::Repo;
-use crate::util::{print_red};
+use crate::testcases::{Repo, Submission};
+use crate::util::{print_green, print_justified, print_red, print_yellow};
 
 pub struct Git {
     cfg: GitCfg,
@@ -12,17 +14,37 @@ pub struct Git {
 impl Git {
     pub fn new(cfg: GitCfg) -> Self { Git { cfg } }
 
-    fn remote_url(&self, repo_path: &str) -> String {
+    pub fn host(&self) -> &str {
+        if self.cfg.host.is_empty() { "github.com" } else { &self.cfg.host }
+    }
+
+    fn fill_template(&self, template: &str, project: &str, student: &str, commit: &str) -> String {
+        template.replace("{host}", self.host())
+            .replace("{org}", &self.cfg.org)
+            .replace("{project}", project)
+            .replace("{student}", student)
+            .replace("{commit}", commit)
+    }
+
+    fn remote_url(&self, project: &str, student: &str) -> String {
+        if let Some(t) = &self.cfg.url_template { return self.fill_template(t, project, student, ""); }
+        let host = self.host();
         match self.cfg.credentials.as_str() {
-            "ssh" => format!("git@github.com:{}/{}.git", self.cfg.org, repo_path),
-            "https" => format!("https://github.com/{}/{}", self.cfg.org, repo_path),
+            "ssh" => format!("git@{}:{}/{}-{}.git", host, self.cfg.org, project, student),
+            "https" => format!("https://{}/{}/{}-{}", host, self.cfg.org, project, student),
             other => {
                 print_red(&format!("unknown Git.credentials: {}\n", other));
-                format!("git@github.com:{}/{}.git", self.cfg.org, repo_path)
+                format!("git@{}:{}/{}-{}.git", host, self.cfg.org, project, student)
             }
         }
     }
 
+    // Browser link to a student's repo at a commit, for comment headers
+    pub fn web_url(&self, project: &str, student: &str, commit: &str) -> String {
+        let t = self.cfg.web_url_template.as_deref().unwrap_or("https://{host}/{org}/{project}-{student}/tree/{commit}");
+        self.fill_template(t, project, student, commit)
+    }
+
     fn run_capture(args: &[&str], cwd: Option<&PathBuf>) -> anyhow::Result<String> {
         let mut cmd = Command::new(args[0]);
         if args.len() > 1 { cmd.args(&args[1..]); }
@@ -78,6 +100,25 @@ impl Git {
         Ok(hash)
     }
 
+    // Hours from the deadline (epoch seconds) to the newest commit on the default branch;
+    // negative when early. Uses the remote branch, so a clone checked out at the deadline still sees later commits.
+    pub fn hours_late(local: &PathBuf, deadline: i64) -> anyhow::Result<f64> {
+        let branch = match Self::run_status(&["git", "rev-parse", "--verify", "--quiet", "origin/HEAD"], Some(local)) {
+            Ok((true, _)) => String::from("origin/HEAD"),
+            _ => format!("origin/{}", Self::get_default_branch(local)?),
+        };
+        let s = Self::run_capture(&["git", "log", "-1", "--format=%ct", &branch], Some(local))?;
+        let ts: i64 = s.trim().parse().map_err(|_| anyhow::anyhow!("No commits on {}", branch))?;
+        Ok((ts - deadline) as f64 / 3600.0)
+    }
+
+    pub fn get_head_hash(local: &PathBuf) -> Option<String> {
+        if !local.is_dir() { return None; }
+        let s = Self::run_capture(&["git", "rev-parse", "HEAD"], Some(local)).ok()?;
+        let h = s.lines().next().unwrap_or("").trim().to_string();
+        if h.len() == 40 || h.len() == 64 { Some(h) } else { None }
+    }
+
     pub fn get_short_hash(local: &PathBuf) -> Option<String> {
         if !local.is_dir() { return None; }
         let s = Self::run_capture(&["git", "rev-parse", "--short", "HEAD"], Some(local)).ok()?;
@@ -85,53 +126,434 @@ impl Git {
         if h.is_empty() { None } else { Some(h) }
     }
 
-    pub fn clone_repo(&self, project: &str, repo: &Repo, date: Option<&str>, verbose: bool) {
+    pub fn clone_repo(&self, project: &str, repo: &Repo, date: Option<&str>, verbose: bool) -> GitOutcome {
         // repo.local_path is ./project-student
-        if repo.local_path.is_dir() {
-            println!("Already exists: {}", repo.local_path.display());
-            return;
-        }
-        let remote = format!("{}-{}", project, repo.student.clone().unwrap_or_default());
-        let url = self.remote_url(&remote);
-        let ok = if verbose {
-            Self::run_ok(&["git", "clone", &url, &repo.local_path.to_string_lossy()], None)
+        if repo.local_path.is_dir() { return GitOutcome::AlreadyExists; }
+        let url = self.remote_url(project, repo.student.as_deref().unwrap_or_default());
+        let dest = repo.local_path.to_string_lossy().to_string();
+        if verbose {
+            if !Self::run_ok(&["git", "clone", &url, &dest], None).unwrap_or(false) { return GitOutcome::NoRemote; }
         } else {
-            Self::run_ok_quiet(&["git", "clone", &url, &repo.local_path.to_string_lossy()], None)
+            match Self::run_status(&["git", "clone", &url, &dest], None) {
+                Ok((true, _)) => {}
+                Ok((false, err)) if Self::is_missing_remote(&err) => return GitOutcome::NoRemote,
+                Ok((false, err)) => return GitOutcome::Failed(Self::last_line(&err)),
+                Err(e) => return GitOutcome::Failed(e.to_string()),
+            }
         }
-            .unwrap_or(false);
-        if !ok { print_red("No remote repo or clone failed\n"); return; }
 
         if let Some(d) = date {
             // Checkout commit before date on default branch
-            match Self::get_default_branch(&repo.local_path) {
-                Ok(branch) => match Self::get_commit_before(&repo.local_path, &branch, d) {
+            if let Ok(branch) = Self::get_default_branch(&repo.local_path) {
+                match Self::get_commit_before(&repo.local_path, &branch, d) {
                     Ok(hash) => {
-                        let _ = if verbose {
+                        let ok = if verbose {
                             Self::run_ok(&["git", "checkout", &hash], Some(&repo.local_path))
                         } else {
                             Self::run_ok_quiet(&["git", "checkout", &hash], Some(&repo.local_path))
                         };
-                    },
+                        if !ok.unwrap_or(false) { return GitOutcome::Failed(format!("checkout {} failed", hash)); }
+                    }
                     Err(_) => {
                         // No commits in range: remove local repo to match Python behavior
-                        let _ = Self::run_ok_quiet(&["rm", "-rf", &repo.local_path.to_string_lossy()], None);
+                        let _ = Self::run_ok_quiet(&["rm", "-rf", &dest], None);
+                        return GitOutcome::NoCommitsBeforeDate;
                     }
-                },
-                Err(_) => {}
+                }
             }
         }
-        println!();
+        GitOutcome::Cloned
     }
 
-    pub fn pull_repo(&self, repo: &Repo) {
+    pub fn pull_repo(&self, repo: &Repo) -> GitOutcome {
         if !repo.local_path.is_dir() {
-            print_red(&format!("Local repo {} does not exist\n", repo.local_path.display()));
-            return;
+            return GitOutcome::Failed(format!("Local repo {} does not exist", repo.local_path.display()));
         }
+        let before = Self::get_short_hash(&repo.local_path).unwrap_or_default();
         if let Ok(branch) = Self::get_default_branch(&repo.local_path) {
-            let _ = Self::run_ok(&["git", "checkout", &branch], Some(&repo.local_path));
+            let _ = Self::run_ok_quiet(&["git", "checkout", &branch], Some(&repo.local_path));
+        }
+        match Self::run_status(&["git", "pull"], Some(&repo.local_path)) {
+            Ok((true, _)) => {}
+            Ok((false, out)) if ["CONFLICT", "would be overwritten", "divergent branches", "Not possible to fast-forward"].iter().any(|m| out.contains(m)) => return GitOutcome::Conflict,
+            Ok((false, out)) => return GitOutcome::Failed(Self::last_line(&out)),
+            Err(e) => return GitOutcome::Failed(e.to_string()),
+        }
+        let after = Self::get_short_hash(&repo.local_path).unwrap_or_default();
+        if before == after { GitOutcome::UpToDate } else { GitOutcome::Updated { from: before, to: after } }
+    }
+
+    // Fetch an existing clone and move it to the last commit before date on branch
+    // (default branch when None), or to the branch tip without a date. Untracked
+    // files in the working tree are left alone.
+    pub fn checkout_repo(&self, repo: &Repo, branch: Option<&str>, date: Option<&str>) -> GitOutcome {
+        let local = &repo.local_path;
+        if !local.is_dir() {
+            return GitOutcome::Failed(format!("Local repo {} does not exist", local.display()));
+        }
+        let before = Self::get_short_hash(local).unwrap_or_default();
+        match Self::run_status(&["git", "fetch", "--quiet", "origin"], Some(local)) {
+            Ok((true, _)) => {}
+            Ok((false, out)) => return GitOutcome::Failed(Self::last_line(&out)),
+            Err(e) => return GitOutcome::Failed(e.to_string()),
+        }
+        let branch = match branch {
+            Some(b) => b.to_string(),
+            None => match Self::get_default_branch(local) {
+                Ok(b) => b,
+                Err(e) => return GitOutcome::Failed(e.to_string()),
+            },
+        };
+        let remote_branch = format!("origin/{}", branch);
+        let steps: Vec<Vec<String>> = match date {
+            Some(d) => match Self::get_commit_before(local, &remote_branch, d) {
+                Ok(hash) => vec![vec!["checkout".into(), "--quiet".into(), hash]],
+                Err(_) => return GitOutcome::NoCommitsBeforeDate,
+            },
+            None => vec![
+                vec!["checkout".into(), "--quiet".into(), branch.clone()],
+                vec!["merge".into(), "--ff-only".into(), "--quiet".into(), remote_branch],
+            ],
+        };
+        for step in steps {
+            let mut args = vec!["git"];
+            args.extend(step.iter().map(|s| s.as_str()));
+            match Self::run_status(&args, Some(local)) {
+                Ok((true, _)) => {}
+                Ok((false, out)) if ["would be overwritten", "Not possible to fast-forward", "diverging"].iter().any(|m| out.contains(m)) => return GitOutcome::Conflict,
+                Ok((false, out)) => return GitOutcome::Failed(Self::last_line(&out)),
+                Err(e) => return GitOutcome::Failed(e.to_string()),
+            }
+        }
+        let after = Self::get_short_hash(local).unwrap_or_default();
+        if before == after { GitOutcome::UpToDate } else { GitOutcome::CheckedOut { from: before, to: after } }
+    }
+
+    // Commit files (source, path in repo) on top of the remote default branch and push them,
+    // either to that branch or to a new one. The clone is returned to whatever it had checked out.
+    pub fn distribute(&self, repo: &Repo, files: &[(PathBuf, String)], branch: Option<&str>, message: &str) -> GitOutcome {
+        let local = &repo.local_path;
+        if !local.is_dir() {
+            return GitOutcome::Failed(format!("Local repo {} does not exist", local.display()));
+        }
+        match Self::run_status(&["git", "status", "--porcelain", "--untracked-files=no"], Some(local)) {
+            Ok((true, out)) if !out.trim().is_empty() => return GitOutcome::Failed("uncommitted changes".into()),
+            Ok((true, _)) => {}
+            Ok((false, out)) => return GitOutcome::Failed(Self::last_line(&out)),
+            Err(e) => return GitOutcome::Failed(e.to_string()),
+        }
+        // Branch name, or the commit when detached (e.g. at a deadline or tag)
+        let original = match Self::run_status(&["git", "symbolic-ref", "--quiet", "--short", "HEAD"], Some(local)) {
+            Ok((true, out)) => out.trim().to_string(),
+            _ => Self::get_head_hash(local).unwrap_or_default(),
+        };
+        match Self::run_status(&["git", "fetch", "--quiet", "origin"], Some(local)) {
+            Ok((true, _)) => {}
+            Ok((false, out)) => return GitOutcome::Failed(Self::last_line(&out)),
+            Err(e) => return GitOutcome::Failed(e.to_string()),
+        }
+        let base = match Self::get_default_branch(local) {
+            Ok(b) => b,
+            Err(e) => return GitOutcome::Failed(e.to_string()),
+        };
+        let target = branch.unwrap_or(&base).to_string();
+        let outcome = self.commit_and_push(local, files, &base, &target, message);
+        if !original.is_empty() { let _ = Self::run_ok_quiet(&["git", "checkout", "--quiet", &original], Some(local)); }
+        outcome
+    }
+
+    fn commit_and_push(&self, local: &PathBuf, files: &[(PathBuf, String)], base: &str, target: &str, message: &str) -> GitOutcome {
+        // Commit on a detached HEAD so no local branch is rewritten; an existing target branch
+        // is built on so that distributing to it again fast-forwards
+        let existing = format!("origin/{}", target);
+        let start = match Self::run_status(&["git", "rev-parse", "--verify", "--quiet", &existing], Some(local)) {
+            Ok((true, _)) => existing,
+            _ => format!("origin/{}", base),
+        };
+        match Self::run_status(&["git", "checkout", "--quiet", "--detach", &start], Some(local)) {
+            Ok((true, _)) => {}
+            Ok((false, out)) => return GitOutcome::Failed(Self::last_line(&out)),
+            Err(e) => return GitOutcome::Failed(e.to_string()),
+        }
+        for (src, dest) in files {
+            let path = local.join(dest);
+            if let Some(dir) = path.parent() {
+                if let Err(e) = std::fs::create_dir_all(dir) { return GitOutcome::Failed(e.to_string()); }
+            }
+            if let Err(e) = std::fs::copy(src, &path) { return GitOutcome::Failed(format!("{}: {}", src.display(), e)); }
+        }
+        let mut add = vec!["git", "add", "--"];
+        add.extend(files.iter().map(|(_, d)| d.as_str()));
+        if let Ok((false, out)) = Self::run_status(&add, Some(local)) { return GitOutcome::Failed(Self::last_line(&out)); }
+        if let Ok((true, _)) = Self::run_status(&["git", "diff", "--cached", "--quiet"], Some(local)) { return GitOutcome::NoChanges; }
+        let refspec = format!("HEAD:refs/heads/{}", target);
+        for step in [vec!["git", "commit", "--quiet", "-m", message], vec!["git", "push", "--quiet", "origin", &refspec]] {
+            match Self::run_status(&step, Some(local)) {
+                Ok((true, _)) => {}
+                Ok((false, out)) => return GitOutcome::Failed(Self::last_line(&out)),
+                Err(e) => return GitOutcome::Failed(e.to_string()),
+            }
+        }
+        GitOutcome::Pushed { branch: target.to_string(), base: base.to_string(), hash: Self::get_short_hash(local).unwrap_or_default() }
+    }
+
+    // Check out the submission tag, applying the fallback when the student did not tag.
+    // Tags are re-fetched with --force since students sometimes move them.
+    pub fn checkout_submission(&self, repo: &Repo, tag: &str, fallback: TagFallback, date: Option<&str>) -> (GitOutcome, Option<Submission>) {
+        let local = &repo.local_path;
+        if !local.is_dir() {
+            return (GitOutcome::Failed(format!("Local repo {} does not exist", local.display())), None);
+        }
+        match Self::run_status(&["git", "fetch", "--quiet", "--tags", "--force", "origin"], Some(local)) {
+            Ok((true, _)) => {}
+            Ok((false, out)) => return (GitOutcome::Failed(Self::last_line(&out)), None),
+            Err(e) => return (GitOutcome::Failed(e.to_string()), None),
+        }
+        let tag_ref = format!("refs/tags/{}", tag);
+        let tagged = matches!(Self::run_status(&["git", "rev-parse", "--verify", "--quiet", &format!("{}^{{commit}}", tag_ref)], Some(local)), Ok((true, _)));
+        let target = if tagged {
+            tag_ref.clone()
+        } else {
+            if fallback == TagFallback::NotSubmitted { return (GitOutcome::NotSubmitted, None); }
+            let branch = match Self::get_default_branch(local) {
+                Ok(b) => format!("origin/{}", b),
+                Err(e) => return (GitOutcome::Failed(e.to_string()), None),
+            };
+            match date {
+                Some(d) => match Self::get_commit_before(local, &branch, d) {
+                    Ok(hash) => hash,
+                    Err(_) => return (GitOutcome::NoCommitsBeforeDate, None),
+                },
+                None => branch,
+            }
+        };
+        match Self::run_status(&["git", "checkout", "--quiet", "--detach", &target], Some(local)) {
+            Ok((true, _)) => {}
+            Ok((false, out)) if out.contains("would be overwritten") => return (GitOutcome::Conflict, None),
+            Ok((false, out)) => return (GitOutcome::Failed(Self::last_line(&out)), None),
+            Err(e) => return (GitOutcome::Failed(e.to_string()), None),
+        }
+        let hash = Self::get_short_hash(local).unwrap_or_default();
+        // creatordate is the tagger date for annotated tags and the commit date for lightweight ones
+        let date_args: Vec<&str> = if tagged {
+            vec!["git", "for-each-ref", "--format=%(creatordate:iso-strict)", &tag_ref]
+        } else {
+            vec!["git", "log", "-1", "--format=%cI", "HEAD"]
+        };
+        let when = Self::run_capture(&date_args, Some(local)).map(|s| s.trim().to_string()).unwrap_or_default();
+        let submission = Submission { tag: if tagged { Some(tag.to_string()) } else { None }, hash: hash.clone(), date: when };
+        let outcome = if tagged { GitOutcome::Tagged { tag: tag.to_string(), hash } } else { GitOutcome::TagMissing { hash } };
+        (outcome, Some(submission))
+    }
+
+    // Integrity signals for the history reachable from HEAD. identities are the student's
+    // known names/logins/emails; backdating allows an hour of clock skew against the parent.
+    pub fn audit_repo(local: &PathBuf, identities: &[String], opts: &AuditOpts) -> anyhow::Result<Vec<AuditFlag>> {
+        let log = Self::run_capture(&["git", "log", "--topo-order", "--reverse", "--numstat", "--format=@%h%x09%p%x09%an%x09%ae%x09%at%x09%ct", "HEAD"], Some(local))?;
+        let mut flags = vec![];
+        // hash -> (author time, commit time)
+        let mut times: HashMap<String, (i64, i64)> = HashMap::new();
+        let mut large: Option<(String, i64, u64)> = None;
+        let check_large = |c: Option<(String, i64, u64)>, flags: &mut Vec<AuditFlag>| {
+            let (Some((hash, ct, lines)), Some(deadline)) = (c, opts.deadline) else { return };
+            let hours_before = (deadline - ct) as f64 / 3600.0;
+            if lines >= opts.large_lines && hours_before >= 0.0 && hours_before <= opts.window_hours {
+                flags.push(AuditFlag { kind: AuditKind::LargeBeforeDeadline, commit: hash, detail: format!("{} lines changed {:.1} hours before the deadline", lines, hours_before) });
+            }
+        };
+        for line in log.lines() {
+            if let Some(rest) = line.strip_prefix('@') {
+                check_large(large.take(), &mut flags);
+                let f: Vec<&str> = rest.split('\t').collect();
+                if f.len() < 6 { anyhow::bail!("Unexpected git log line: {}", line); }
+                let (hash, parents, name, email) = (f[0], f[1], f[2], f[3]);
+                let at: i64 = f[4].parse().unwrap_or(0);
+                let ct: i64 = f[5].parse().unwrap_or(0);
+                if !author_matches(name, email, identities) && !author_matches(name, email, &opts.allow) {
+                    flags.push(AuditFlag { kind: AuditKind::ForeignAuthor, commit: hash.to_string(), detail: format!("{} <{}>", name, email) });
+                }
+                if let Some((pat, pct)) = parents.split(' ').next().and_then(|p| times.get(p)) {
+                    if at < pat - 3600 || ct < pct - 3600 {
+                        flags.push(AuditFlag { kind: AuditKind::Backdated, commit: hash.to_string(), detail: format!("dated {:.1} hours before its parent", (pct.max(pat) - ct.min(at)) as f64 / 3600.0) });
+                    }
+                }
+                times.insert(hash.to_string(), (at, ct));
+                large = Some((hash.to_string(), ct, 0));
+            } else {
+                let mut parts = line.split('\t');
+                let (Some(a), Some(r)) = (parts.next(), parts.next()) else { continue };
+                let (Ok(a), Ok(r)) = (a.parse::<u64>(), r.parse::<u64>()) else { continue };
+                if let Some(c) = large.as_mut() { c.2 += a + r; }
+            }
+        }
+        check_large(large.take(), &mut flags);
+        Ok(flags)
+    }
+
+    // Run quietly; returns success and the combined stdout/stderr
+    fn run_status(args: &[&str], cwd: Option<&PathBuf>) -> anyhow::Result<(bool, String)> {
+        let mut cmd = Command::new(args[0]);
+        if args.len() > 1 { cmd.args(&args[1..]); }
+        if let Some(dir) = cwd { cmd.current_dir(dir); }
+        cmd.stdin(Stdio::null());
+        let out = cmd.output()?;
+        let mut text = String::from_utf8_lossy(&out.stdout).to_string();
+        text.push_str(&String::from_utf8_lossy(&out.stderr));
+        Ok((out.status.success(), text))
+    }
+
+    fn is_missing_remote(err: &str) -> bool {
+        let lower = err.to_lowercase();
+        ["not found", "does not exist", "could not read from remote", "does not appear to be a git repository"].iter().any(|m| lower.contains(m))
+    }
+
+    fn last_line(text: &str) -> String {
+        text.lines().map(|l| l.trim()).rfind(|l| !l.is_empty()).unwrap_or("unknown error").to_string()
+    }
+}
+
+// Email identities must equal the author email. Other identities (GitHub IDs, logins) match the
+// author name, or the user of a GitHub noreply address ("123+user@users.noreply.github.com").
+fn author_matches(name: &str, email: &str, identities: &[String]) -> bool {
+    let email = email.trim().to_lowercase();
+    let github_user = email.strip_suffix("@users.noreply.github.com").map(|local| local.rsplit('+').next().unwrap_or(local));
+    let name = name.trim().to_lowercase();
+    identities.iter().map(|i| i.trim().to_lowercase()).filter(|i| !i.is_empty()).any(|id| {
+        if id.contains('@') { id == email } else { id == name || github_user == Some(id.as_str()) }
+    })
+}
+
+pub struct AuditOpts {
+    // Always accepted authors, e.g. the instructor who committed the starter code
+    pub allow: Vec<String>,
+    pub deadline: Option<i64>,
+    pub large_lines: u64,
+    pub window_hours: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum AuditKind {
+    ForeignAuthor,
+    Backdated,
+    LargeBeforeDeadline,
+}
+
+impl std::fmt::Display for AuditKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AuditKind::ForeignAuthor => write!(f, "Foreign author"),
+            AuditKind::Backdated => write!(f, "Backdated"),
+            AuditKind::LargeBeforeDeadline => write!(f, "Large commit before deadline"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AuditFlag {
+    pub kind: AuditKind,
+    pub commit: String,
+    pub detail: String,
+}
+
+// What to grade when a repo lacks the submission tag
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TagFallback {
+    Deadline,
+    NotSubmitted,
+}
+
+impl TagFallback {
+    pub fn parse(s: &str) -> anyhow::Result<Self> {
+        match s {
+            "deadline" => Ok(TagFallback::Deadline),
+            "not-submitted" => Ok(TagFallback::NotSubmitted),
+            other => anyhow::bail!("Unknown tag fallback \"{}\" (expected \"deadline\" or \"not-submitted\")", other),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum GitOutcome {
+    Cloned,
+    AlreadyExists,
+    NoRemote,
+    NoCommitsBeforeDate,
+    Updated { from: String, to: String },
+    CheckedOut { from: String, to: String },
+    Tagged { tag: String, hash: String },
+    TagMissing { hash: String },
+    NotSubmitted,
+    Pushed { branch: String, base: String, hash: String },
+    NoChanges,
+    UpToDate,
+    Conflict,
+    Failed(String),
+}
+
+impl GitOutcome {
+    // Outcome name without details, used to group the summary
+    pub fn kind(&self) -> &'static str {
+        match self {
+            GitOutcome::Cloned => "Cloned",
+            GitOutcome::AlreadyExists => "Already exists",
+            GitOutcome::NoRemote => "No remote repo",
+            GitOutcome::NoCommitsBeforeDate => "No commits before date",
+            GitOutcome::Updated { .. } => "Updated",
+            GitOutcome::CheckedOut { .. } => "Checked out",
+            GitOutcome::Tagged { .. } => "At tag",
+            GitOutcome::TagMissing { .. } => "Tag missing, used fallback",
+            GitOutcome::NotSubmitted => "Not submitted",
+            GitOutcome::Pushed { .. } => "Pushed",
+            GitOutcome::NoChanges => "No changes",
+            GitOutcome::UpToDate => "Up to date",
+            GitOutcome::Conflict => "Conflict",
+            GitOutcome::Failed(_) => "Failed",
+        }
+    }
+
+    pub fn is_ok(&self) -> bool {
+        matches!(self, GitOutcome::Cloned | GitOutcome::AlreadyExists | GitOutcome::Updated { .. } | GitOutcome::CheckedOut { .. } | GitOutcome::Tagged { .. } | GitOutcome::Pushed { .. } | GitOutcome::NoChanges | GitOutcome::UpToDate)
+    }
+
+    pub fn print(&self) {
+        let text = format!("{}\n", self);
+        match self {
+            GitOutcome::Cloned | GitOutcome::Updated { .. } | GitOutcome::CheckedOut { .. } | GitOutcome::Tagged { .. } | GitOutcome::Pushed { .. } | GitOutcome::UpToDate => print_green(&text),
+            GitOutcome::AlreadyExists | GitOutcome::NoCommitsBeforeDate | GitOutcome::TagMissing { .. } | GitOutcome::NotSubmitted | GitOutcome::NoChanges => print_yellow(&text),
+            _ => print_red(&text),
         }
-        let _ = Self::run_ok(&["git", "pull"], Some(&repo.local_path));
+    }
+}
+
+impl std::fmt::Display for GitOutcome {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GitOutcome::Updated { from, to } => write!(f, "Updated {}..{}", from, to),
+            GitOutcome::CheckedOut { from, to } => write!(f, "Checked out {}..{}", from, to),
+            GitOutcome::Tagged { tag, hash } => write!(f, "At tag {} ({})", tag, hash),
+            GitOutcome::TagMissing { hash } => write!(f, "Tag missing, used fallback ({})", hash),
+            GitOutcome::Pushed { branch, hash, .. } => write!(f, "Pushed {} to {}", hash, branch),
+            GitOutcome::Failed(msg) => write!(f, "Failed: {}", msg),
+            other => write!(f, "{}", other.kind()),
+        }
+    }
+}
+
+// Print a count per outcome, naming the students for anything that needs attention
+pub fn print_summary(outcomes: &[(String, GitOutcome)]) {
+    let mut groups: Vec<(&'static str, Vec<&str>, bool)> = vec![];
+    for (label, o) in outcomes {
+        match groups.iter_mut().find(|g| g.0 == o.kind()) {
+            Some(g) => g.1.push(label),
+            None => groups.push((o.kind(), vec![label], o.is_ok())),
+        }
+    }
+    let width = groups.iter().map(|g| g.0.len()).max().unwrap_or(0) + 2;
+    println!("\nSummary (n = {})", outcomes.len());
+    for (kind, labels, ok) in groups {
+        print_justified(kind, width);
+        if ok { println!("{}", labels.len()); } else { println!("{}  {}", labels.len(), labels.join(" ")); }
     }
 }
 
@@ -142,10 +564,25 @@ mod tests {
 
     #[test]
     fn remote_url_formats() {
-        let g_ssh = Git::new(GitCfg { org: "orgx".into(), credentials: "ssh".into() });
-        let g_https = Git::new(GitCfg { org: "orgx".into(), credentials: "https".into() });
-        assert_eq!(g_ssh.remote_url("projx-alice"), "git@github.com:orgx/projx-alice.git");
-        assert_eq!(g_https.remote_url("projx-alice"), "https://github.com/orgx/projx-alice");
+        let g_ssh = Git::new(GitCfg { org: "orgx".into(), credentials: "ssh".into(), ..Default::default() });
+        let g_https = Git::new(GitCfg { org: "orgx".into(), credentials: "https".into(), ..Default::default() });
+        assert_eq!(g_ssh.remote_url("projx", "alice"), "git@github.com:orgx/projx-alice.git");
+        assert_eq!(g_https.remote_url("projx", "alice"), "https://github.com/orgx/projx-alice");
+    }
+
+    #[test]
+    fn url_templates_for_other_hosts() {
+        let g = Git::new(GitCfg {
+            org: "cs101".into(),
+            credentials: "ssh".into(),
+            host: "gitlab.example.edu".into(),
+            url_template: Some("git@{host}:{org}/{project}-{student}.git".into()),
+            web_url_template: Some("https://{host}/{org}/{project}-{student}/-/tree/{commit}".into()),
+        });
+        assert_eq!(g.remote_url("projx", "alice"), "git@gitlab.example.edu:cs101/projx-alice.git");
+        assert_eq!(g.web_url("projx", "alice", "abc123"), "https://gitlab.example.edu/cs101/projx-alice/-/tree/abc123");
+        let plain = Git::new(GitCfg { org: "orgx".into(), ..Default::default() });
+        assert_eq!(plain.web_url("projx", "alice", "abc123"), "https://github.com/orgx/projx-alice/tree/abc123");
     }
 
     #[test]
@@ -1,23 +1,171 @@
 use serde::Deserialize;
 
 use crate::config::GithubCfg;
-use crate::testcases::RepoResult;
+use crate::http::{Http, HttpOpts};
+use crate::actions::{ArtifactResults, CheckRunOutput};
+use crate::testcases::{RepoResult, TestRunner};
 use crate::util::{print_yellow};
 
 #[derive(Debug, Deserialize)]
 struct ArtifactsList { artifacts: Vec<Artifact> }
 
 #[derive(Debug, Deserialize, Clone)]
-struct WorkflowRun { id: u64 }
+struct WorkflowRun {
+    id: u64,
+    #[serde(default)] head_branch: Option<String>,
+}
 
 #[derive(Debug, Deserialize, Clone)]
 struct Artifact {
     #[allow(dead_code)]
     id: u64,
+    #[serde(default)] name: String,
+    #[serde(default)] expired: bool,
+    #[serde(default)] created_at: Option<String>,
     archive_download_url: String,
     workflow_run: WorkflowRun,
 }
 
+impl Artifact {
+    fn created_ts(&self) -> Option<i64> {
+        self.created_at.as_deref().and_then(|c| chrono::DateTime::parse_from_rfc3339(c).ok()).map(|t| t.timestamp())
+    }
+}
+
+// One workflow run; status is queued, in_progress or completed
+#[derive(Debug, Deserialize, Clone)]
+pub struct ActionRun {
+    #[serde(default)] pub id: u64,
+    #[serde(default)] name: String,
+    #[serde(default)] path: String,
+    #[serde(default)] pub status: String,
+    #[serde(default)] pub conclusion: Option<String>,
+    #[serde(default)] pub run_attempt: u32,
+    #[serde(default)] pub html_url: String,
+    #[serde(default)] run_started_at: Option<String>,
+}
+
+impl ActionRun {
+    // Workflow name, or its file name/path such as "grade.yml"
+    fn matches_workflow(&self, workflow: &str) -> bool {
+        let file = self.path.rsplit('/').next().unwrap_or("");
+        self.name == workflow || self.path == workflow || file == workflow
+    }
+
+    pub fn is_completed(&self) -> bool { self.status == "completed" }
+}
+
+#[derive(Debug, Deserialize)]
+struct RunsList { workflow_runs: Vec<ActionRun> }
+
+#[derive(Debug, Deserialize)]
+struct RepoInfo { default_branch: String }
+
+// How `actions rerun` restarts a student's workflow
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RerunMode {
+    // Re-run every job of the latest run
+    All,
+    // Re-run only the failed jobs of the latest run
+    Failed,
+    // Start a new run with workflow_dispatch, which picks up workflow changes
+    Dispatch,
+}
+
+impl RerunMode {
+    pub fn parse(s: &str) -> anyhow::Result<Self> {
+        match s {
+            "all" => Ok(RerunMode::All),
+            "failed" => Ok(RerunMode::Failed),
+            "dispatch" => Ok(RerunMode::Dispatch),
+            other => anyhow::bail!("Unknown rerun mode \"{}\" (expected all, failed or dispatch)", other),
+        }
+    }
+}
+
+// The run a rerun started, for wait_for_run to find
+#[derive(Debug, Clone)]
+pub enum Triggered {
+    // A new attempt of an existing run
+    Attempt { run_id: u64, attempt: u32 },
+    // A dispatched run; it is the first one newer than after_id
+    Dispatch { workflow: String, after_id: u64 },
+}
+
+// Which artifact to grade; the newest unexpired artifact matching every set field wins
+#[derive(Debug, Clone, Default)]
+pub struct ArtifactFilter {
+    pub name: Option<String>,
+    // Workflow name, or its file name/path such as "grade.yml"
+    pub workflow: Option<String>,
+    pub branch: Option<String>,
+    // Only artifacts created at or before this time (epoch seconds)
+    pub before: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OrgRepo { name: String }
+
+// Where `feedback github` posts results
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FeedbackMode {
+    // One "Autograder results" issue, updated in place
+    Issue,
+    // A comment on the graded commit
+    CommitComment,
+    // A comment on the Classroom "Feedback" pull request, updated in place
+    Pr,
+}
+
+impl FeedbackMode {
+    pub fn parse(s: &str) -> anyhow::Result<Self> {
+        match s {
+            "issue" => Ok(FeedbackMode::Issue),
+            "commit-comment" => Ok(FeedbackMode::CommitComment),
+            "pr" => Ok(FeedbackMode::Pr),
+            other => anyhow::bail!("Unknown feedback mode \"{}\" (expected \"issue\", \"commit-comment\" or \"pr\")", other),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FeedbackAction { Create, Update, Unchanged }
+
+// Hidden first line of every post, so later runs find their own comments
+pub const FEEDBACK_MARKER: &str = "<!-- grade-rs feedback -->";
+pub const FEEDBACK_ISSUE_TITLE: &str = "Autograder results";
+const FEEDBACK_PR_TITLE: &str = "Feedback";
+
+// Markdown feedback: the per-test table, then the comment as Canvas would show it
+pub fn feedback_body(project: &str, rr: &RepoResult) -> String {
+    format!("{}\n{}\n```\n{}\n```\n", FEEDBACK_MARKER, crate::actions::step_summary(project, rr), rr.comment.trim_end())
+}
+
+#[derive(Debug, Deserialize)]
+struct IssueItem {
+    number: u64,
+    #[serde(default)] title: String,
+    #[serde(default)] body: Option<String>,
+    #[serde(default)] html_url: String,
+    #[serde(default)] pull_request: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CommentItem {
+    id: u64,
+    #[serde(default)] body: String,
+    #[serde(default)] html_url: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Posted { #[serde(default)] html_url: String }
+
+#[derive(Debug, Deserialize)]
+struct CheckRunCreated { id: u64, #[serde(default)] html_url: String }
+
+// Most annotations the checks API accepts per request
+const ANNOTATIONS_PER_REQUEST: usize = 50;
+
 #[derive(Debug, Deserialize)]
 struct JobsList { jobs: Vec<Job> }
 
@@ -29,30 +177,56 @@ pub struct Github {
     org: String,
     project: String,
     _verbose: bool,
-    client: reqwest::blocking::Client,
+    http: Http,
+    filter: ArtifactFilter,
 }
 
 impl Github {
     pub fn new(cfg: GithubCfg, org: String, project: String, verbose: bool) -> anyhow::Result<Self> {
-        let client = reqwest::blocking::Client::builder()
-            .user_agent("autograder-rust/0.1")
-            .build()?;
-        Ok(Github { cfg, org, project, _verbose: verbose, client })
+        let http = Http::new(Self::headers(&cfg), HttpOpts::default())?;
+        Ok(Github { cfg, org, project, _verbose: verbose, http, filter: ArtifactFilter::default() })
     }
 
+    pub fn set_artifact_filter(&mut self, filter: ArtifactFilter) { self.filter = filter; }
+
+    // Revalidate GETs against an on-disk ETag cache; 304s do not count against the rate limit
+    pub fn set_http_cache(&mut self, dir: Option<std::path::PathBuf>) { self.http.set_cache_dir(dir); }
+
     fn base(&self) -> String {
         if self.cfg.host_name.contains("://") { self.cfg.host_name.clone() } else { format!("https://{}", self.cfg.host_name) }
     }
 
-    fn headers(&self) -> reqwest::header::HeaderMap {
+    fn headers(cfg: &GithubCfg) -> reqwest::header::HeaderMap {
         use reqwest::header::{HeaderMap, HeaderValue, ACCEPT, AUTHORIZATION};
         let mut headers = HeaderMap::new();
         headers.insert(ACCEPT, HeaderValue::from_static("application/vnd.github+json"));
-        let token = format!("Bearer {}", self.cfg.access_token);
+        let token = format!("Bearer {}", cfg.access_token);
         headers.insert(AUTHORIZATION, HeaderValue::from_str(&token).unwrap_or(HeaderValue::from_static("")));
         headers
     }
 
+    // Students with a "<project>-<student>" repo in the org, sorted
+    pub fn list_project_students(&self) -> anyhow::Result<Vec<String>> {
+        let prefix = format!("{}-", self.project);
+        let mut url = format!("{}/orgs/{}/repos?per_page=100", self.base(), self.org);
+        let mut students = vec![];
+        loop {
+            let res = self.http.get(&url)?;
+            if !res.status().is_success() { anyhow::bail!("Listing repos for {} returned {}", self.org, res.status()); }
+            let next = crate::canvas::next_link_from_header(res.headers().get("Link"));
+            let repos: Vec<OrgRepo> = res.json()?;
+            students.extend(repos.into_iter().filter_map(|r| r.name.strip_prefix(&prefix).map(|s| s.to_string())).filter(|s| !s.is_empty()));
+            // GitHub returns absolute next links; accept relative ones too
+            match next {
+                Some(n) if n.starts_with("http") => url = n,
+                Some(n) => url = format!("{}{}", self.base(), n),
+                None => break,
+            }
+        }
+        students.sort();
+        Ok(students)
+    }
+
     fn make_artifacts_url(&self, student: &str) -> String {
         format!("{}/repos/{}/{}-{}/actions/artifacts", self.base(), self.org, self.project, student)
     }
@@ -61,20 +235,284 @@ impl Github {
         format!("{}/repos/{}/{}-{}/actions/runs", self.base(), self.org, self.project, student)
     }
 
-    fn get_first_artifact(&self, student: &str) -> anyhow::Result<Option<Artifact>> {
-        let url = self.make_artifacts_url(student);
-        let res = self.client.get(url).headers(self.headers()).send()?;
-        if !res.status().is_success() {
-            print_yellow(&format!("Accessing artifacts for {} returned {}\n", student, res.status()));
-            return Ok(None);
+    fn list_artifacts(&self, student: &str) -> anyhow::Result<Option<Vec<Artifact>>> {
+        let mut params = vec![("per_page", String::from("100"))];
+        if let Some(n) = &self.filter.name { params.push(("name", n.clone())); }
+        let mut url = reqwest::Url::parse_with_params(&self.make_artifacts_url(student), &params)?.to_string();
+        let mut all = vec![];
+        loop {
+            let res = self.http.get(&url)?;
+            if !res.status().is_success() {
+                print_yellow(&format!("Accessing artifacts for {} returned {}\n", student, res.status()));
+                return Ok(None);
+            }
+            let next = crate::canvas::next_link_from_header(res.headers().get("Link"));
+            let list: ArtifactsList = res.json()?;
+            all.extend(list.artifacts);
+            match next {
+                Some(n) if n.starts_with("http") => url = n,
+                Some(n) => url = format!("{}{}", self.base(), n),
+                None => break,
+            }
+        }
+        Ok(Some(all))
+    }
+
+    fn run_matches_workflow(&self, student: &str, run_id: u64, workflow: &str) -> anyhow::Result<bool> {
+        let url = format!("{}/{}", self.make_runs_url(student), run_id);
+        let res = self.http.get(&url)?;
+        if !res.status().is_success() { return Ok(false); }
+        let run: ActionRun = res.json()?;
+        Ok(run.matches_workflow(workflow))
+    }
+
+    fn select_artifact(&self, student: &str) -> anyhow::Result<Option<Artifact>> {
+        let Some(mut artifacts) = self.list_artifacts(student)? else { return Ok(None) };
+        // Newest first; artifacts without a timestamp sort last
+        artifacts.sort_by_key(|a| std::cmp::Reverse(a.created_ts().unwrap_or(i64::MIN)));
+        let f = &self.filter;
+        let mut run_checked: std::collections::HashMap<u64, bool> = std::collections::HashMap::new();
+        for a in artifacts {
+            if a.expired { continue; }
+            if f.name.as_ref().map(|n| &a.name != n).unwrap_or(false) { continue; }
+            if f.branch.is_some() && a.workflow_run.head_branch != f.branch { continue; }
+            if let Some(before) = f.before {
+                if a.created_ts().map(|t| t > before).unwrap_or(true) { continue; }
+            }
+            if let Some(w) = &f.workflow {
+                let ok = match run_checked.get(&a.workflow_run.id) {
+                    Some(ok) => *ok,
+                    None => {
+                        let ok = self.run_matches_workflow(student, a.workflow_run.id, w)?;
+                        run_checked.insert(a.workflow_run.id, ok);
+                        ok
+                    }
+                };
+                if !ok { continue; }
+            }
+            return Ok(Some(a));
+        }
+        Ok(None)
+    }
+
+    fn make_repo_url(&self, student: &str) -> String {
+        format!("{}/repos/{}/{}-{}", self.base(), self.org, self.project, student)
+    }
+
+    // Every page of a list endpoint
+    fn get_all<T: serde::de::DeserializeOwned>(&self, first: String) -> anyhow::Result<Vec<T>> {
+        let mut url = first;
+        let mut all = vec![];
+        loop {
+            let res = self.http.get(&url)?;
+            if !res.status().is_success() { anyhow::bail!("GET {} returned {}", url, res.status()); }
+            let next = crate::canvas::next_link_from_header(res.headers().get("Link"));
+            all.extend(res.json::<Vec<T>>()?);
+            match next {
+                Some(n) if n.starts_with("http") => url = n,
+                Some(n) => url = format!("{}{}", self.base(), n),
+                None => break,
+            }
+        }
+        Ok(all)
+    }
+
+    fn write_json(&self, method: reqwest::Method, url: &str, body: serde_json::Value) -> anyhow::Result<String> {
+        let res = self.http.send_json(method.clone(), url, &body)?;
+        if !res.status().is_success() { anyhow::bail!("{} {} returned {}", method, url, res.status()); }
+        Ok(res.json::<Posted>().map(|p| p.html_url).unwrap_or_default())
+    }
+
+    // Post body to the student's repo unless an identical post is already there.
+    // With dry_run nothing is written; the returned action says what would happen.
+    pub fn post_feedback(&self, student: &str, mode: FeedbackMode, body: &str, commit: Option<&str>, dry_run: bool) -> anyhow::Result<(FeedbackAction, Option<String>)> {
+        let repo = self.make_repo_url(student);
+        match mode {
+            FeedbackMode::Issue => {
+                let issues: Vec<IssueItem> = self.get_all(format!("{}/issues?state=open&per_page=100", repo))?;
+                let existing = issues.into_iter().find(|i| i.pull_request.is_none() && i.title == FEEDBACK_ISSUE_TITLE);
+                match existing {
+                    Some(i) if i.body.as_deref() == Some(body) => Ok((FeedbackAction::Unchanged, Some(i.html_url))),
+                    Some(i) if dry_run => Ok((FeedbackAction::Update, Some(i.html_url))),
+                    Some(i) => {
+                        let url = self.write_json(reqwest::Method::PATCH, &format!("{}/issues/{}", repo, i.number), serde_json::json!({ "body": body }))?;
+                        Ok((FeedbackAction::Update, Some(url)))
+                    }
+                    None if dry_run => Ok((FeedbackAction::Create, None)),
+                    None => {
+                        let url = self.write_json(reqwest::Method::POST, &format!("{}/issues", repo), serde_json::json!({ "title": FEEDBACK_ISSUE_TITLE, "body": body }))?;
+                        Ok((FeedbackAction::Create, Some(url)))
+                    }
+                }
+            }
+            FeedbackMode::CommitComment => {
+                let Some(sha) = commit else { anyhow::bail!("no graded commit recorded for {}", student) };
+                let comments: Vec<CommentItem> = self.get_all(format!("{}/commits/{}/comments?per_page=100", repo, sha))?;
+                if let Some(c) = comments.into_iter().find(|c| c.body == body) { return Ok((FeedbackAction::Unchanged, Some(c.html_url))); }
+                if dry_run { return Ok((FeedbackAction::Create, None)); }
+                let url = self.write_json(reqwest::Method::POST, &format!("{}/commits/{}/comments", repo, sha), serde_json::json!({ "body": body }))?;
+                Ok((FeedbackAction::Create, Some(url)))
+            }
+            FeedbackMode::Pr => {
+                let pulls: Vec<IssueItem> = self.get_all(format!("{}/pulls?state=open&per_page=100", repo))?;
+                let Some(pr) = pulls.into_iter().find(|p| p.title == FEEDBACK_PR_TITLE) else { anyhow::bail!("no open \"{}\" pull request", FEEDBACK_PR_TITLE) };
+                let comments: Vec<CommentItem> = self.get_all(format!("{}/issues/{}/comments?per_page=100", repo, pr.number))?;
+                match comments.into_iter().find(|c| c.body.starts_with(FEEDBACK_MARKER)) {
+                    Some(c) if c.body == body => Ok((FeedbackAction::Unchanged, Some(c.html_url))),
+                    Some(c) if dry_run => Ok((FeedbackAction::Update, Some(c.html_url))),
+                    Some(c) => {
+                        let url = self.write_json(reqwest::Method::PATCH, &format!("{}/issues/comments/{}", repo, c.id), serde_json::json!({ "body": body }))?;
+                        Ok((FeedbackAction::Update, Some(url)))
+                    }
+                    None if dry_run => Ok((FeedbackAction::Create, Some(pr.html_url))),
+                    None => {
+                        let url = self.write_json(reqwest::Method::POST, &format!("{}/issues/{}/comments", repo, pr.number), serde_json::json!({ "body": body }))?;
+                        Ok((FeedbackAction::Create, Some(url)))
+                    }
+                }
+            }
+        }
+    }
+
+    // Open a pull request from head into base; an already-open one for the same branch is reused.
+    // Returns whether a new PR was created and its link.
+    pub fn open_pull_request(&self, student: &str, head: &str, base: &str, title: &str, body: &str) -> anyhow::Result<(bool, String)> {
+        let repo = self.make_repo_url(student);
+        let query = reqwest::Url::parse_with_params(&format!("{}/pulls", repo), &[("state", "open"), ("head", &format!("{}:{}", self.org, head)), ("base", base)])?;
+        let open: Vec<IssueItem> = self.get_all(query.to_string())?;
+        if let Some(pr) = open.into_iter().next() { return Ok((false, pr.html_url)); }
+        let url = self.write_json(reqwest::Method::POST, &format!("{}/pulls", repo), serde_json::json!({ "title": title, "head": head, "base": base, "body": body }))?;
+        Ok((true, url))
+    }
+
+    fn select_run_artifact(&self, student: &str, run: &ActionRun) -> anyhow::Result<Option<Artifact>> {
+        let url = format!("{}/{}/artifacts?per_page=100", self.make_runs_url(student), run.id);
+        let res = self.http.get(&url)?;
+        if !res.status().is_success() { anyhow::bail!("Listing artifacts for run {} returned {}", run.id, res.status()); }
+        let mut artifacts = res.json::<ArtifactsList>()?.artifacts;
+        artifacts.sort_by_key(|a| std::cmp::Reverse(a.created_ts().unwrap_or(i64::MIN)));
+        let started = run.run_started_at.as_deref().and_then(|t| chrono::DateTime::parse_from_rfc3339(t).ok()).map(|t| t.timestamp());
+        Ok(artifacts.into_iter().find(|a| {
+            !a.expired
+                && self.filter.name.as_ref().map(|n| &a.name == n).unwrap_or(true)
+                && started.map(|t| a.created_ts().map(|c| c >= t).unwrap_or(false)).unwrap_or(true)
+        }))
+    }
+
+    fn latest_run(&self, student: &str, workflow: Option<&str>, branch: Option<&str>) -> anyhow::Result<Option<ActionRun>> {
+        let mut params = vec![("per_page", String::from("100"))];
+        if let Some(b) = branch { params.push(("branch", b.to_string())); }
+        let url = reqwest::Url::parse_with_params(&self.make_runs_url(student), &params)?;
+        let res = self.http.get(url.as_str())?;
+        if !res.status().is_success() { anyhow::bail!("Listing runs for {} returned {}", student, res.status()); }
+        let runs = res.json::<RunsList>()?.workflow_runs;
+        Ok(runs.into_iter().find(|r| workflow.map(|w| r.matches_workflow(w)).unwrap_or(true)))
+    }
+
+    fn dispatch_runs(&self, student: &str, workflow: &str) -> anyhow::Result<Vec<ActionRun>> {
+        let url = format!("{}/actions/workflows/{}/runs?event=workflow_dispatch&per_page=20", self.make_repo_url(student), workflow);
+        let res = self.http.get(&url)?;
+        if !res.status().is_success() { anyhow::bail!("Listing {} runs for {} returned {}", workflow, student, res.status()); }
+        Ok(res.json::<RunsList>()?.workflow_runs)
+    }
+
+    // Re-run the latest run of the workflow (on branch, if given), or dispatch a new run of it
+    // on branch or the default branch. Dispatch needs the workflow's file name.
+    pub fn rerun(&self, student: &str, mode: RerunMode, workflow: Option<&str>, branch: Option<&str>) -> anyhow::Result<Triggered> {
+        let repo = self.make_repo_url(student);
+        if mode == RerunMode::Dispatch {
+            let workflow = workflow.unwrap_or("grade.yml");
+            let file = workflow.rsplit('/').next().unwrap_or(workflow).to_string();
+            // Run ids only grow, so the new run is the first one above the current newest
+            let after_id = self.dispatch_runs(student, &file)?.iter().map(|r| r.id).max().unwrap_or(0);
+            let git_ref = match branch {
+                Some(b) => b.to_string(),
+                None => {
+                    let res = self.http.get(&repo)?;
+                    if !res.status().is_success() { anyhow::bail!("GET {} returned {}", repo, res.status()); }
+                    res.json::<RepoInfo>()?.default_branch
+                }
+            };
+            let url = format!("{}/actions/workflows/{}/dispatches", repo, file);
+            let res = self.http.send_json(reqwest::Method::POST, &url, &serde_json::json!({ "ref": git_ref }))?;
+            if !res.status().is_success() { anyhow::bail!("Dispatching {} returned {}", file, res.status()); }
+            return Ok(Triggered::Dispatch { workflow: file, after_id });
         }
-        let list: ArtifactsList = res.json()?;
-        Ok(list.artifacts.into_iter().next())
+        let Some(run) = self.latest_run(student, workflow, branch)? else { anyhow::bail!("no workflow runs found") };
+        if !run.is_completed() { anyhow::bail!("run {} is still {}", run.id, run.status); }
+        let endpoint = if mode == RerunMode::Failed { "rerun-failed-jobs" } else { "rerun" };
+        let url = format!("{}/{}/{}", self.make_runs_url(student), run.id, endpoint);
+        let res = self.http.send_json(reqwest::Method::POST, &url, &serde_json::json!({}))?;
+        if !res.status().is_success() { anyhow::bail!("Re-running run {} returned {}", run.id, res.status()); }
+        Ok(Triggered::Attempt { run_id: run.id, attempt: run.run_attempt })
+    }
+
+    // Current state of a triggered run, or None while a dispatched run has not appeared yet
+    pub fn triggered_run(&self, student: &str, t: &Triggered) -> anyhow::Result<Option<ActionRun>> {
+        match t {
+            Triggered::Attempt { run_id, attempt } => {
+                let url = format!("{}/{}", self.make_runs_url(student), run_id);
+                let res = self.http.get(&url)?;
+                if !res.status().is_success() { anyhow::bail!("GET run {} returned {}", run_id, res.status()); }
+                let run: ActionRun = res.json()?;
+                // Until the new attempt registers, the run still shows the old one
+                Ok(if run.run_attempt > *attempt { Some(run) } else { None })
+            }
+            Triggered::Dispatch { workflow, after_id } => {
+                let runs = self.dispatch_runs(student, workflow)?;
+                Ok(runs.into_iter().filter(|r| r.id > *after_id).min_by_key(|r| r.id))
+            }
+        }
+    }
+
+    // Poll every `poll` until the run completes; the last state seen (if any) is returned at the deadline
+    pub fn wait_for_run(&self, student: &str, t: &Triggered, deadline: std::time::Instant, poll: std::time::Duration) -> anyhow::Result<Option<ActionRun>> {
+        loop {
+            let run = self.triggered_run(student, t)?;
+            let now = std::time::Instant::now();
+            if run.as_ref().map(|r| r.is_completed()).unwrap_or(false) || now >= deadline { return Ok(run); }
+            std::thread::sleep(poll.min(deadline - now));
+        }
+    }
+
+    // Re-run the student's workflow, wait until the deadline for it to finish, and grade its artifact
+    pub fn rerun_results(&self, student: &str, mode: RerunMode, workflow: Option<&str>, branch: Option<&str>, deadline: std::time::Instant, poll: std::time::Duration) -> RepoResult {
+        let waited = self.rerun(student, mode, workflow, branch).and_then(|t| self.wait_for_run(student, &t, deadline, poll));
+        let comment = match waited {
+            Ok(Some(run)) if run.is_completed() => return self.get_run_results(student, &run),
+            Ok(Some(run)) => format!("Workflow run did not finish in time: {}", run.html_url),
+            Ok(None) => String::from("Workflow run did not start in time"),
+            Err(e) => format!("GitHub error: {}", e),
+        };
+        print_yellow(&format!("{}\n", comment));
+        RepoResult { comment, results: vec![], score: 0, student: Some(student.to_string()), build_err: None, submission: None, late: None, commit: None }
+    }
+
+    // Create a completed check run on sha in repository ("owner/name"). Annotations past the
+    // first 50 are added with follow-up updates. Needs an app token such as Actions' GITHUB_TOKEN.
+    pub fn create_check_run(&self, repository: &str, sha: &str, name: &str, conclusion: &str, output: &CheckRunOutput) -> anyhow::Result<String> {
+        let url = format!("{}/repos/{}/check-runs", self.base(), repository);
+        let mut chunks = output.annotations.chunks(ANNOTATIONS_PER_REQUEST);
+        let body = serde_json::json!({
+            "name": name,
+            "head_sha": sha,
+            "status": "completed",
+            "conclusion": conclusion,
+            "output": { "title": output.title, "summary": output.summary, "annotations": chunks.next().unwrap_or_default() },
+        });
+        let res = self.http.send_json(reqwest::Method::POST, &url, &body)?;
+        if !res.status().is_success() { anyhow::bail!("Creating check run on {} returned {}: {}", repository, res.status(), res.text()); }
+        let created: CheckRunCreated = res.json()?;
+        for chunk in chunks {
+            let body = serde_json::json!({ "output": { "title": output.title, "summary": output.summary, "annotations": chunk } });
+            self.write_json(reqwest::Method::PATCH, &format!("{}/{}", url, created.id), body)?;
+        }
+        Ok(created.html_url)
     }
 
     fn get_action_run_summary_url(&self, student: &str, run_id: u64) -> anyhow::Result<String> {
         let jobs_url = format!("{}/{}/jobs", self.make_runs_url(student), run_id);
-        let res = self.client.get(jobs_url).headers(self.headers()).send()?;
+        let res = self.http.get(&jobs_url)?;
         if !res.status().is_success() {
             print_yellow(&format!("No jobs found for {} run {}\n", student, run_id));
             return Ok(format!("https://github.com/{}/{}-{}/actions/runs/{}", self.org, self.project, student, run_id));
@@ -84,35 +522,44 @@ impl Github {
         Ok(format!("https://github.com/{}/{}-{}/actions/runs/{}#summary-{}", self.org, self.project, student, run_id, job_id))
     }
 
-    fn download_artifact_grade(&self, artifact: &Artifact) -> anyhow::Result<f64> {
-        let res = self.client.get(&artifact.archive_download_url).headers(self.headers()).send()?;
+    fn download_artifact_results(&self, artifact: &Artifact) -> anyhow::Result<ArtifactResults> {
+        let res = self.http.download(&artifact.archive_download_url)?;
         if !res.status().is_success() { anyhow::bail!("Failed to download artifact"); }
-        let bytes = res.bytes()?;
-        let reader = std::io::Cursor::new(bytes);
+        let reader = std::io::Cursor::new(res.bytes());
         let mut zip = zip::ZipArchive::new(reader)?;
         let mut file = zip.by_name("grade-results.json")?;
         let mut s = String::new();
         use std::io::Read;
         file.read_to_string(&mut s)?;
-        let v: serde_json::Value = serde_json::from_str(&s)?;
-        let grade = v.get("grade").and_then(|g| g.as_f64()).unwrap_or(0.0);
-        Ok(grade)
+        Ok(serde_json::from_str(&s)?)
     }
 
     pub fn get_action_results(&self, student: &str) -> RepoResult {
+        self.results_from(student, self.select_artifact(student))
+    }
+
+    // Results from the artifact a particular run uploaded, ignoring earlier attempts
+    pub fn get_run_results(&self, student: &str, run: &ActionRun) -> RepoResult {
+        self.results_from(student, self.select_run_artifact(student, run))
+    }
+
+    fn results_from(&self, student: &str, selected: anyhow::Result<Option<Artifact>>) -> RepoResult {
         // Minimal RepoResult: score + comment link
-        let mut rr = RepoResult { comment: String::new(), results: vec![], score: 0, student: Some(student.to_string()), build_err: None };
-        match self.get_first_artifact(student) {
+        let mut rr = RepoResult { comment: String::new(), results: vec![], score: 0, student: Some(student.to_string()), build_err: None, submission: None, late: None, commit: None };
+        match selected {
             Ok(Some(artifact)) => {
                 let run_id = artifact.workflow_run.id;
-                let grade = self.download_artifact_grade(&artifact).unwrap_or_else(|e| { print_yellow(&format!("Analyzing artifact: {}\n", e)); 0.0 });
+                let ar = self.download_artifact_results(&artifact).unwrap_or_else(|e| { print_yellow(&format!("Analyzing artifact: {}\n", e)); ArtifactResults::default() });
                 let link = self.get_action_run_summary_url(student, run_id).unwrap_or_else(|_| String::from(""));
-                rr.score = grade.round() as i64; // cast to i64 to fit current schema
-                rr.comment = link;
+                ar.apply(&mut rr);
+                // Grade-only artifacts keep the bare run link as their comment
+                rr.comment = if rr.results.is_empty() && rr.build_err.is_none() { link } else {
+                    format!("Test results from this GitHub Actions run: {}\n\n{}", link, TestRunner::make_comment_static(&rr))
+                };
             }
             Ok(None) => {
-                rr.comment = "No artifacts found".into();
-                print_yellow("No artifacts found\n");
+                rr.comment = "No matching artifact found".into();
+                print_yellow("No matching artifact found\n");
             }
             Err(e) => {
                 rr.comment = format!("GitHub error: {}", e);
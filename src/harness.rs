@@ -0,0 +1,143 @@
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use std::collections::BTreeMap;
+
+use crate::testcases::TcResult;
+
+// One test case reported by a unit-test harness (JUnit XML or TAP)
+#[derive(Debug, Clone, PartialEq)]
+pub struct HarnessCase {
+    pub name: String,
+    pub passed: bool,
+    pub message: Option<String>,
+}
+
+fn attr(e: &BytesStart, key: &[u8]) -> Option<String> {
+    e.attributes().filter_map(|a| a.ok())
+        .find(|a| a.key.as_ref() == key)
+        .and_then(|a| a.unescape_value().ok().map(|v| v.to_string()))
+}
+
+fn junit_case_name(e: &BytesStart) -> String {
+    let name = attr(e, b"name").unwrap_or_default();
+    match attr(e, b"classname") {
+        Some(cls) if !cls.is_empty() && !name.is_empty() => format!("{}.{}", cls, name),
+        _ => name,
+    }
+}
+
+pub fn parse_junit(xml: &str) -> anyhow::Result<Vec<HarnessCase>> {
+    let mut reader = Reader::from_str(xml);
+    reader.trim_text(true);
+    let mut cases = vec![];
+    let mut current: Option<HarnessCase> = None;
+    // Set while inside <failure>/<error> so its text becomes the message
+    let mut in_failure = false;
+    loop {
+        match reader.read_event().map_err(|e| anyhow::anyhow!("Invalid JUnit XML at {}: {}", reader.buffer_position(), e))? {
+            Event::Start(e) => match e.name().as_ref() {
+                b"testcase" => current = Some(HarnessCase { name: junit_case_name(&e), passed: true, message: None }),
+                b"failure" | b"error" => {
+                    if let Some(c) = current.as_mut() { c.passed = false; c.message = attr(&e, b"message"); }
+                    in_failure = true;
+                }
+                _ => {}
+            },
+            Event::Empty(e) => match e.name().as_ref() {
+                b"testcase" => cases.push(HarnessCase { name: junit_case_name(&e), passed: true, message: None }),
+                b"failure" | b"error" => {
+                    if let Some(c) = current.as_mut() { c.passed = false; c.message = attr(&e, b"message"); }
+                }
+                _ => {}
+            },
+            Event::Text(t) if in_failure => {
+                if let Some(c) = current.as_mut() {
+                    let text = t.unescape().map(|s| s.to_string()).unwrap_or_default();
+                    c.message = Some(match c.message.take() { Some(m) if !m.is_empty() => format!("{}\n{}", m, text), _ => text });
+                }
+            }
+            Event::CData(t) if in_failure => {
+                if let Some(c) = current.as_mut() {
+                    let text = String::from_utf8_lossy(&t.into_inner()).to_string();
+                    c.message = Some(match c.message.take() { Some(m) if !m.is_empty() => format!("{}\n{}", m, text), _ => text });
+                }
+            }
+            Event::End(e) => match e.name().as_ref() {
+                b"testcase" => { if let Some(c) = current.take() { cases.push(c); } }
+                b"failure" | b"error" => in_failure = false,
+                _ => {}
+            },
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    Ok(cases)
+}
+
+// Parse a TAP stream. Lines after a "not ok" that are comments or indented (YAML
+// diagnostics) become that case's message. SKIP and TODO directives count as passing.
+// Cases promised by the "1..N" plan but never reported (the harness crashed or bailed
+// out) are added as failures, so the rubric is split over the planned count.
+pub fn parse_tap(text: &str) -> Vec<HarnessCase> {
+    let mut cases: Vec<HarnessCase> = vec![];
+    let mut collecting = false;
+    let mut planned = 0;
+    for line in text.lines() {
+        if let Some(n) = line.strip_prefix("1..").and_then(|r| r.split_whitespace().next()).and_then(|n| n.parse::<usize>().ok()) {
+            planned = n;
+            collecting = false;
+            continue;
+        }
+        let (passed, rest) = if let Some(r) = line.strip_prefix("not ok") { (false, r) }
+            else if let Some(r) = line.strip_prefix("ok") { (true, r) }
+            else {
+                if collecting && (line.starts_with('#') || line.starts_with(' ') || line.starts_with('\t')) {
+                    let diag = line.trim_start_matches('#').trim();
+                    if !diag.is_empty() && diag != "---" && diag != "..." {
+                        if let Some(c) = cases.last_mut() {
+                            c.message = Some(match c.message.take() { Some(m) => format!("{}\n{}", m, diag), None => diag.to_string() });
+                        }
+                    }
+                } else { collecting = false; }
+                continue;
+            };
+        if !rest.is_empty() && !rest.starts_with(' ') { continue; }
+        let rest = rest.trim();
+        let (body, directive) = match rest.split_once('#') { Some((b, d)) => (b.trim(), d.trim().to_uppercase()), None => (rest, String::new()) };
+        let (num, desc) = match body.split_once(' ') { Some((n, d)) => (n, d.trim_start_matches('-').trim()), None => (body, "") };
+        let name = if desc.is_empty() { if num.is_empty() { (cases.len() + 1).to_string() } else { num.to_string() } } else { desc.to_string() };
+        let passed = passed || directive.starts_with("SKIP") || directive.starts_with("TODO");
+        collecting = !passed;
+        cases.push(HarnessCase { name, passed, message: None });
+    }
+    for n in cases.len() + 1..=planned {
+        cases.push(HarnessCase { name: n.to_string(), passed: false, message: Some("Not reported".into()) });
+    }
+    cases
+}
+
+// Turn harness cases into results. With a points map, each named case earns its
+// points and cases outside the map are ignored; otherwise the rubric is split
+// equally (remainder goes to the first cases so the total matches).
+pub fn score_cases(prefix: &str, cases: &[HarnessCase], points: &BTreeMap<String, i64>, rubric: i64) -> Vec<TcResult> {
+    let mut results = vec![];
+    if !points.is_empty() {
+        for (name, pts) in points {
+            let test = format!("{}/{}", prefix, name);
+            match cases.iter().find(|c| &c.name == name) {
+                Some(c) if c.passed => results.push(TcResult { rubric: *pts, score: *pts, test, test_err: None, ..Default::default() }),
+                Some(c) => results.push(TcResult { rubric: *pts, score: 0, test, test_err: Some(c.message.clone().unwrap_or_else(|| "Failed".into())), ..Default::default() }),
+                None => results.push(TcResult { rubric: *pts, score: 0, test, test_err: Some("Test not found in report".into()), ..Default::default() }),
+            }
+        }
+        return results;
+    }
+    let n = cases.len() as i64;
+    for (i, c) in cases.iter().enumerate() {
+        let pts = rubric / n + if (i as i64) < rubric % n { 1 } else { 0 };
+        let test = format!("{}/{}", prefix, c.name);
+        if c.passed { results.push(TcResult { rubric: pts, score: pts, test, test_err: None, ..Default::default() }); }
+        else { results.push(TcResult { rubric: pts, score: 0, test, test_err: Some(c.message.clone().unwrap_or_else(|| "Failed".into())), ..Default::default() }); }
+    }
+    results
+}
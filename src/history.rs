@@ -0,0 +1,96 @@
+use serde::Serialize;
+use std::collections::HashSet;
+use std::path::Path;
+use std::process::Command;
+
+use crate::util::print_justified;
+
+// Commit activity for one student's repo, from the log reachable from HEAD
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct History {
+    pub student: String,
+    pub commits: usize,
+    pub active_days: usize,
+    pub first_commit: Option<String>,
+    pub last_commit: Option<String>,
+    pub lines_added: u64,
+    pub lines_removed: u64,
+    pub largest_commit: Option<String>,
+    pub largest_commit_lines: u64,
+}
+
+fn git_log(local: &Path, args: &[&str]) -> anyhow::Result<String> {
+    let out = Command::new("git").args(args).current_dir(local).output()?;
+    if !out.status.success() {
+        anyhow::bail!("git {} failed: {}", args[0], String::from_utf8_lossy(&out.stderr).trim());
+    }
+    Ok(String::from_utf8_lossy(&out.stdout).to_string())
+}
+
+pub fn commit_count(local: &Path) -> anyhow::Result<usize> {
+    let s = git_log(local, &["rev-list", "--count", "HEAD"])?;
+    s.trim().parse().map_err(|_| anyhow::anyhow!("Unexpected rev-list output: {}", s.trim()))
+}
+
+// Author dates are used, since they reflect when the student did the work
+pub fn repo_history(student: &str, local: &Path) -> anyhow::Result<History> {
+    let log = git_log(local, &["log", "--reverse", "--numstat", "--format=@%h %aI", "HEAD"])?;
+    let mut h = History { student: student.to_string(), ..Default::default() };
+    let mut days: HashSet<String> = HashSet::new();
+    // (hash, lines changed) of the commit being read
+    let mut current: Option<(String, u64)> = None;
+    fn finish(h: &mut History, c: Option<(String, u64)>) {
+        if let Some((hash, lines)) = c {
+            if h.largest_commit.is_none() || lines > h.largest_commit_lines {
+                h.largest_commit = Some(hash);
+                h.largest_commit_lines = lines;
+            }
+        }
+    }
+    for line in log.lines() {
+        if let Some(rest) = line.strip_prefix('@') {
+            finish(&mut h, current.take());
+            let (hash, date) = rest.split_once(' ').unwrap_or((rest, ""));
+            h.commits += 1;
+            days.insert(date.chars().take(10).collect());
+            if h.first_commit.is_none() { h.first_commit = Some(date.to_string()); }
+            h.last_commit = Some(date.to_string());
+            current = Some((hash.to_string(), 0));
+        } else {
+            // numstat: added<TAB>removed<TAB>path; binary files show "-"
+            let mut parts = line.split('\t');
+            let (Some(a), Some(r)) = (parts.next(), parts.next()) else { continue };
+            let (Ok(a), Ok(r)) = (a.parse::<u64>(), r.parse::<u64>()) else { continue };
+            h.lines_added += a;
+            h.lines_removed += r;
+            if let Some(c) = current.as_mut() { c.1 += a + r; }
+        }
+    }
+    finish(&mut h, current.take());
+    h.active_days = days.len();
+    Ok(h)
+}
+
+pub fn print_table(rows: &[History]) {
+    let width = rows.iter().map(|r| r.student.len()).max().unwrap_or(0).max("Student".len()) + 2;
+    print_justified("Student", width);
+    println!("{:>7} {:>5} {:>10} {:>10} {:>8} {:>8}  Largest", "Commits", "Days", "First", "Last", "Added", "Removed");
+    for r in rows {
+        let day = |d: &Option<String>| d.as_deref().map(|s| s.chars().take(10).collect()).unwrap_or_else(|| String::from("-"));
+        let largest = r.largest_commit.as_deref().map(|c| format!("{} ({} lines)", c, r.largest_commit_lines)).unwrap_or_default();
+        print_justified(&r.student, width);
+        println!("{:>7} {:>5} {:>10} {:>10} {:>8} {:>8}  {}", r.commits, r.active_days, day(&r.first_commit), day(&r.last_commit), r.lines_added, r.lines_removed, largest);
+    }
+}
+
+pub fn write_csv(path: &str, rows: &[History]) -> anyhow::Result<()> {
+    let mut w = csv::Writer::from_path(path)?;
+    for r in rows { w.serialize(r)?; }
+    w.flush()?;
+    Ok(())
+}
+
+pub fn write_json(path: &str, rows: &[History]) -> anyhow::Result<()> {
+    std::fs::write(path, serde_json::to_string_pretty(rows)?)?;
+    Ok(())
+}
@@ -0,0 +1,239 @@
+use reqwest::header::{HeaderMap, HeaderValue, ETAG, IF_NONE_MATCH, LINK, RETRY_AFTER};
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::PathBuf;
+use std::sync::{Condvar, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::util::print_yellow;
+
+// Longest we will sleep for a single rate-limit pause
+const MAX_WAIT: Duration = Duration::from_secs(3600);
+
+// Response with the body already read, so cached bodies and live ones look the same
+pub struct Response {
+    status: StatusCode,
+    headers: HeaderMap,
+    body: Vec<u8>,
+}
+
+impl Response {
+    pub fn status(&self) -> StatusCode { self.status }
+    pub fn headers(&self) -> &HeaderMap { &self.headers }
+    pub fn bytes(&self) -> &[u8] { &self.body }
+    pub fn text(&self) -> String { String::from_utf8_lossy(&self.body).to_string() }
+    pub fn json<T: serde::de::DeserializeOwned>(&self) -> anyhow::Result<T> { Ok(serde_json::from_slice(&self.body)?) }
+}
+
+#[derive(Serialize, Deserialize)]
+struct CachedResponse {
+    etag: String,
+    #[serde(default)] link: Option<String>,
+    body: String,
+}
+
+// Counting semaphore bounding requests in flight across threads
+struct Slots { free: Mutex<usize>, cv: Condvar }
+
+struct SlotGuard<'a>(&'a Slots);
+
+impl Slots {
+    fn acquire(&self) -> SlotGuard<'_> {
+        let mut free = self.free.lock().unwrap();
+        while *free == 0 { free = self.cv.wait(free).unwrap(); }
+        *free -= 1;
+        SlotGuard(self)
+    }
+}
+
+impl Drop for SlotGuard<'_> {
+    fn drop(&mut self) {
+        *self.0.free.lock().unwrap() += 1;
+        self.0.cv.notify_one();
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct HttpOpts {
+    pub max_in_flight: usize,
+    pub retries: u32,
+    // First retry delay; doubled on each further attempt
+    pub backoff: Duration,
+}
+
+impl Default for HttpOpts {
+    fn default() -> Self { HttpOpts { max_in_flight: 8, retries: 4, backoff: Duration::from_secs(1) } }
+}
+
+// Blocking HTTP client shared by the GitHub and Canvas code: bounded concurrency,
+// retries with exponential backoff, rate-limit pauses, and optional ETag caching of GETs
+pub struct Http {
+    client: reqwest::blocking::Client,
+    slots: Slots,
+    retries: u32,
+    backoff: Duration,
+    cache_dir: Option<PathBuf>,
+    // Set when a host says to slow down; every thread waits until then
+    pause_until: Mutex<Option<Instant>>,
+}
+
+impl Http {
+    pub fn new(headers: HeaderMap, opts: HttpOpts) -> anyhow::Result<Self> {
+        let client = reqwest::blocking::Client::builder()
+            .user_agent("autograder-rust/0.1")
+            .default_headers(headers)
+            .build()?;
+        Ok(Http {
+            client,
+            slots: Slots { free: Mutex::new(opts.max_in_flight.max(1)), cv: Condvar::new() },
+            retries: opts.retries,
+            backoff: opts.backoff,
+            cache_dir: None,
+            pause_until: Mutex::new(None),
+        })
+    }
+
+    pub fn set_cache_dir(&mut self, dir: Option<PathBuf>) { self.cache_dir = dir; }
+
+    // GET with If-None-Match when a cached copy exists; a 304 is served from the cache
+    pub fn get(&self, url: &str) -> anyhow::Result<Response> {
+        let cached = self.cache_load(url);
+        let res = self.send(true, || {
+            let mut req = self.client.get(url);
+            if let Some(c) = &cached { req = req.header(IF_NONE_MATCH, c.etag.as_str()); }
+            req
+        })?;
+        if res.status == StatusCode::NOT_MODIFIED {
+            if let Some(c) = cached {
+                let mut headers = HeaderMap::new();
+                if let Some(l) = c.link.as_deref().and_then(|l| HeaderValue::from_str(l).ok()) { headers.insert(LINK, l); }
+                return Ok(Response { status: StatusCode::OK, headers, body: c.body.into_bytes() });
+            }
+        }
+        if res.status.is_success() { self.cache_store(url, &res); }
+        Ok(res)
+    }
+
+    // GET that bypasses the cache, for large or one-off downloads
+    pub fn download(&self, url: &str) -> anyhow::Result<Response> {
+        self.send(true, || self.client.get(url))
+    }
+
+    // Not retried on a 5xx or timeout: a Canvas grade PUT with a comment adds the comment again each time it lands
+    pub fn put_form(&self, url: &str, params: &[(&str, String)]) -> anyhow::Result<Response> {
+        self.send(false, || self.client.put(url).form(params))
+    }
+
+    // POST or PATCH a JSON body. Only throttled requests are retried, since a 5xx may still have created something.
+    pub fn send_json(&self, method: reqwest::Method, url: &str, body: &serde_json::Value) -> anyhow::Result<Response> {
+        self.send(false, || self.client.request(method.clone(), url).json(body))
+    }
+
+    fn send<F>(&self, retry_server_errors: bool, build: F) -> anyhow::Result<Response>
+    where F: Fn() -> reqwest::blocking::RequestBuilder {
+        let mut attempt = 0;
+        loop {
+            self.wait_for_pause();
+            let sent = {
+                let _slot = self.slots.acquire();
+                build().send().and_then(|r| {
+                    let status = r.status();
+                    let headers = r.headers().clone();
+                    r.bytes().map(|b| Response { status, headers, body: b.to_vec() })
+                })
+            };
+            let res = match sent {
+                Ok(r) => r,
+                Err(e) if attempt < self.retries && (e.is_connect() || (retry_server_errors && e.is_timeout())) => {
+                    std::thread::sleep(self.backoff_for(attempt));
+                    attempt += 1;
+                    continue;
+                }
+                Err(e) => return Err(e.into()),
+            };
+            if let Some(wait) = self.throttle_wait(&res, attempt) {
+                if attempt < self.retries {
+                    print_yellow(&format!("Rate limited; waiting {}s\n", wait.as_secs()));
+                    self.pause_for(wait);
+                    attempt += 1;
+                    continue;
+                }
+            } else if retry_server_errors && res.status.is_server_error() && attempt < self.retries {
+                std::thread::sleep(self.backoff_for(attempt));
+                attempt += 1;
+                continue;
+            }
+            // Out of quota after this request: hold the next ones until the window resets
+            if header_str(&res.headers, &["x-ratelimit-remaining"]) == Some("0") {
+                if let Some(wait) = reset_wait(&res.headers) { self.pause_for(wait); }
+            }
+            return Ok(res);
+        }
+    }
+
+    fn backoff_for(&self, attempt: u32) -> Duration { self.backoff * 2u32.saturating_pow(attempt) }
+
+    // How long to wait before retrying a throttled response, or None if it was not throttled.
+    // GitHub uses 429 or 403 with rate-limit headers; Canvas uses 403 "Rate Limit Exceeded".
+    fn throttle_wait(&self, res: &Response, attempt: u32) -> Option<Duration> {
+        let throttled = match res.status {
+            StatusCode::TOO_MANY_REQUESTS => true,
+            StatusCode::FORBIDDEN => res.headers.contains_key(RETRY_AFTER)
+                || header_str(&res.headers, &["x-ratelimit-remaining", "x-rate-limit-remaining"]).and_then(|v| v.trim().parse::<f64>().ok()).map(|r| r <= 0.0).unwrap_or(false)
+                || res.text().contains("Rate Limit Exceeded"),
+            _ => false,
+        };
+        if !throttled { return None; }
+        let wait = header_str(&res.headers, &[RETRY_AFTER.as_str()]).and_then(|v| v.trim().parse::<u64>().ok()).map(Duration::from_secs)
+            .or_else(|| reset_wait(&res.headers))
+            .unwrap_or_else(|| self.backoff_for(attempt));
+        Some(wait.min(MAX_WAIT))
+    }
+
+    fn pause_for(&self, wait: Duration) {
+        let until = Instant::now() + wait.min(MAX_WAIT);
+        let mut p = self.pause_until.lock().unwrap();
+        if p.map(|cur| cur < until).unwrap_or(true) { *p = Some(until); }
+    }
+
+    fn wait_for_pause(&self) {
+        let until = *self.pause_until.lock().unwrap();
+        if let Some(until) = until {
+            let now = Instant::now();
+            if until > now { std::thread::sleep(until - now); }
+        }
+    }
+
+    fn cache_path(&self, url: &str) -> Option<PathBuf> {
+        let dir = self.cache_dir.as_ref()?;
+        let key: String = Sha256::digest(url.as_bytes()).iter().map(|b| format!("{:02x}", b)).collect();
+        Some(dir.join(format!("{}.json", key)))
+    }
+
+    fn cache_load(&self, url: &str) -> Option<CachedResponse> {
+        let s = std::fs::read_to_string(self.cache_path(url)?).ok()?;
+        serde_json::from_str(&s).ok()
+    }
+
+    // Only JSON-sized text bodies with an ETag are worth keeping
+    fn cache_store(&self, url: &str, res: &Response) {
+        let Some(path) = self.cache_path(url) else { return };
+        let Some(etag) = header_str(&res.headers, &[ETAG.as_str()]) else { return };
+        let Ok(body) = String::from_utf8(res.body.clone()) else { return };
+        let entry = CachedResponse { etag: etag.to_string(), link: header_str(&res.headers, &[LINK.as_str()]).map(|l| l.to_string()), body };
+        if let Some(dir) = path.parent() { let _ = std::fs::create_dir_all(dir); }
+        if let Ok(s) = serde_json::to_string(&entry) { let _ = std::fs::write(path, s); }
+    }
+}
+
+fn header_str<'a>(headers: &'a HeaderMap, names: &[&str]) -> Option<&'a str> {
+    names.iter().find_map(|n| headers.get(*n).and_then(|v| v.to_str().ok()))
+}
+
+// Time until x-ratelimit-reset (epoch seconds), at least one second
+fn reset_wait(headers: &HeaderMap) -> Option<Duration> {
+    let reset: u64 = header_str(headers, &["x-ratelimit-reset"])?.trim().parse().ok()?;
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).ok()?.as_secs();
+    Some(Duration::from_secs(reset.saturating_sub(now).max(1)))
+}
@@ -8,4 +8,12 @@ pub mod github;
 pub mod canvas;
 pub mod dates;
 pub mod rollup;
+pub mod files;
+pub mod harness;
+pub mod http;
+pub mod history;
+pub mod cache;
+pub mod roster;
+pub mod archive;
+pub mod actions;
 
@@ -8,8 +8,16 @@ mod github;
 mod canvas;
 mod dates;
 mod rollup;
+mod files;
+mod harness;
+mod http;
+mod history;
+mod cache;
+mod roster;
+mod archive;
+mod actions;
 
-use crate::cli::{Cli, Commands};
+use crate::cli::{ActionsCmd, ArchiveCmd, Cli, Commands, FeedbackCmd, RosterCmd, WorkflowCmd};
 use crate::config::Config;
 use crate::testcases::{TestRunner, Repo};
 use crate::util::{print_green, print_red};
@@ -29,7 +37,7 @@ fn main() {
     };
 
     match &cli.command {
-        Commands::Test { project, test_name, verbose, very_verbose, unified_diff, quiet, no_color } => {
+        Commands::Test { project, test_name, verbose, very_verbose, unified_diff, quiet, no_color, artifact, check_run } => {
             util::set_color_enabled(!*no_color && std::env::var("NO_COLOR").is_err());
             if *verbose {
                 if let Some(dir) = cfg_path.parent() {
@@ -41,50 +49,101 @@ fn main() {
             if *quiet { runner.set_quiet(true); }
             let repo = Repo::local(".".into(), runner.project_subdir());
             let res = runner.test_repo(&repo, test_name.as_deref());
+            // Under Actions, record results for `class -g` even when the run itself failed
+            let rr = match &res {
+                Ok(rr) => rr.clone(),
+                Err(e) => testcases::RepoResult { comment: String::new(), results: vec![], score: 0, student: None, build_err: Some(e.to_string()), submission: None, late: None, commit: None },
+            };
+            if let Some(path) = artifact {
+                if let Err(e) = actions::ArtifactResults::from_repo_result(&rr).write(path) { print_red(&format!("{}: {}\n", path, e)); std::process::exit(3); }
+            }
+            if let Err(e) = actions::write_step_summary(&project_name, &rr) { print_red(&format!("GITHUB_STEP_SUMMARY: {}\n", e)); }
+            if *check_run {
+                match publish_check_run(&config, &project_name, &rr, runner.build_output(), runner.project_subdir()) {
+                    Ok(url) => println!("Check run: {}", url),
+                    Err(e) => { print_red(&format!("Check run: {}\n", e)); std::process::exit(3); }
+                }
+            }
             if let Err(e) = res {
                 print_red(&format!("{}\n", e));
                 std::process::exit(1);
             }
         }
-        Commands::Class { project, verbose, very_verbose, unified_diff, github_action, students, by_date, jobs, quiet: _, no_color } => {
+        Commands::Class { project, verbose, very_verbose, unified_diff, github_action, students, by_date, jobs, quiet: _, no_color, rerun_failures, tag, tag_fallback, incremental, artifact_name, workflow, branch, rerun, timeout } => {
             util::set_color_enabled(!*no_color && std::env::var("NO_COLOR").is_err());
-            let list: Vec<String> = if let Some(list) = students { list.clone() } else { config.config.students.clone() };
-            if list.is_empty() { print_red("No students provided and Config.students is empty\n"); std::process::exit(2); }
+            let list = student_list_or_exit(&config, students);
 
             let project_name = project.clone().unwrap_or_else(|| util::project_from_cwd());
 
             if *github_action {
                 // Use GitHub API path
-                let gh = match github::Github::new(config.github.clone(), config.git.org.clone(), project_name.clone(), *verbose) {
+                let mut gh = match github::Github::new(config.github.clone(), config.git.org.clone(), project_name.clone(), *verbose) {
                     Ok(g) => g,
                     Err(e) => { print_red(&format!("GitHub client init failed: {}\n", e)); std::process::exit(2); }
                 };
-                let mut class_results = vec![];
+                // With -d, grade the latest artifact created before the selected deadline
+                let runner = TestRunner::new(&config.test, *verbose, *very_verbose, *unified_diff, project_name.clone());
+                let Some(DateChoice { suffix: suffix_opt, date, .. }) = resolve_date(&config, &project_name, *by_date, &None) else { return };
+                let before = match date.as_deref().map(dates::parse_deadline).transpose() {
+                    Ok(t) => t,
+                    Err(e) => { print_red(&format!("{}\n", e)); std::process::exit(2); }
+                };
+                gh.set_artifact_filter(github::ArtifactFilter { name: artifact_name.clone(), workflow: workflow.clone(), branch: branch.clone(), before });
+                gh.set_http_cache(config.config.http_cache_dir());
+                // With --rerun, every student's workflow runs again and the new run's artifact is graded
+                let rerun_mode = match rerun.as_deref().map(github::RerunMode::parse).transpose() {
+                    Ok(m) => m,
+                    Err(e) => { print_red(&format!("{}\n", e)); std::process::exit(2); }
+                };
+                let deadline = std::time::Instant::now() + std::time::Duration::from_secs(*timeout);
                 let repos: Vec<Repo> = list.iter().map(|s| Repo::student(project_name.clone(), s.clone(), None, None)).collect();
-                let longest = repos.iter().map(|r| r.display_label.len()).max().unwrap_or(0) + 1;
-                for s in list.iter() {
-                    let r = Repo::student(project_name.clone(), s.clone(), None, None);
-                    util::print_justified(&r.display_label, longest);
-                    let rr = gh.get_action_results(s);
-                    println!("{}", rr.score);
-                    class_results.push(rr);
-                }
+                // Requests in flight are capped by the HTTP layer, so -j only sets how many students are fetched at once
+                let threads = if *verbose { 1 } else { jobs.unwrap_or(8) };
+                let fetched = run_ordered(&repos, threads, |r| {
+                    let student = r.student.as_deref().unwrap_or_default();
+                    match rerun_mode {
+                        Some(mode) => gh.rerun_results(student, mode, workflow.as_deref(), branch.as_deref(), deadline, RUN_POLL),
+                        None => gh.get_action_results(student),
+                    }
+                }, |rr| {
+                    // Grade-only artifacts have no per-test results to show
+                    if rr.results.is_empty() { println!("{}", rr.score); } else { print_summary(rr); }
+                });
+                let class_results: Vec<testcases::RepoResult> = fetched.into_iter().map(|(_, rr)| rr).collect();
+                runner.print_histogram(&class_results);
                 // Persist results
-                let runner = TestRunner::new(&config.test, *verbose, *very_verbose, *unified_diff, project_name.clone());
-                if let Err(e) = runner.write_class_json(&class_results, None) { print_red(&format!("{}\n", e)); std::process::exit(3); }
+                if let Err(e) = runner.write_class_json(&class_results, suffix_opt.as_deref()) { print_red(&format!("{}\n", e)); std::process::exit(3); }
             } else {
                 // Local test runner path
                 let mut runner = TestRunner::new(&config.test, *verbose, *very_verbose, *unified_diff, project_name.clone());
                 // Suppress internal per-test and trailing prints; we'll print summaries ourselves
                 runner.set_quiet(true);
-                let (suffix_opt, _date_opt) = if *by_date {
-                let d = match dates::Dates::from_tests_path(&runner.tests_path, &project_name) {
-                    Ok(d) => d,
-                    Err(e) => { print_red(&format!("{}\n", e)); std::process::exit(2); }
-                };
-                match d.select() { Some(sel) => (Some(sel.suffix.clone()), Some(sel.date.clone())), None => { return; } }
-            } else { (None, None) };
+                let Some(DateChoice { suffix: suffix_opt, date: date_opt, late_policy }) = resolve_date(&config, &project_name, *by_date, &None) else { return };
                 let repos: Vec<Repo> = list.into_iter().map(|s| Repo::student(project_name.clone(), s, runner.project_subdir(), suffix_opt.clone())).collect();
+                // With --rerun-failures, start from the previous results and re-execute only failed tests
+                let previous: std::collections::HashMap<String, testcases::RepoResult> = if *rerun_failures {
+                    let path = runner.class_json_path(suffix_opt.as_deref());
+                    let data = match std::fs::read_to_string(&path) {
+                        Ok(s) => s,
+                        Err(e) => { print_red(&format!("{} does not exist. Run \"grade-rs class -p {}\" first ({})\n", path, project_name, e)); std::process::exit(2); }
+                    };
+                    let prev: Vec<testcases::RepoResult> = match serde_json::from_str(&data) {
+                        Ok(v) => v,
+                        Err(e) => { print_red(&format!("Failed to parse {}: {}\n", path, e)); std::process::exit(2); }
+                    };
+                    prev.into_iter().filter_map(|rr| rr.student.clone().map(|s| (s, rr))).collect()
+                } else { std::collections::HashMap::new() };
+                // With --incremental, repos whose HEAD was already graded against the same tests reuse that result
+                let cache_path = cache::ResultCache::path_for(&runner.class_json_path(suffix_opt.as_deref()));
+                let cache = if *incremental {
+                    let tests_hash = match runner.tests_fingerprint() {
+                        Ok(h) => h,
+                        Err(e) => { print_red(&format!("{}\n", e)); std::process::exit(2); }
+                    };
+                    Some(std::sync::Mutex::new(cache::ResultCache::load(&cache_path, &tests_hash)))
+                } else { None };
+                let opts = GradeOpts { tag: resolve_tag(&config, &project_name, tag, tag_fallback), date: date_opt, late_policy, cache };
+                let g = git::Git::new(config.git.clone());
                 let longest = repos.iter().map(|r| r.display_label.len()).max().unwrap_or(0) + 1;
                 // Avoid interleaved stdout noise when verbose; run single-threaded then
                 let threads = if *verbose || *very_verbose { 1 } else { jobs.unwrap_or_else(num_cpus) };
@@ -94,17 +153,12 @@ fn main() {
                     for r in &repos {
                         let mut runner_local = TestRunner::new(&config.test, *verbose, *very_verbose, *unified_diff, project_name.clone());
                         runner_local.set_quiet(true);
-                        match runner_local.test_repo(r, None) {
+                        let prev = r.student.as_ref().and_then(|s| previous.get(s));
+                        let res = grade_repo(&mut runner_local, &g, r, prev, &opts);
+                        match res {
                             Ok(rr) => {
                                 util::print_justified(&r.display_label, longest);
-                                if rr.results.is_empty() { println!("{}", rr.comment); }
-                                else {
-                                    for t in &rr.results {
-                                        let tok = crate::util::format_pass_fail(&t.test, t.rubric, t.score);
-                                        if t.score == t.rubric { crate::util::print_green(&tok); } else { crate::util::print_red(&tok); }
-                                    }
-                                    println!("{}", crate::testcases::TestRunner::make_earned_avail_static(&rr.results));
-                                }
+                                print_summary(&rr);
                                 class_results.push((r.clone(), rr));
                             }
                             Err(e) => print_red(&format!("{}\n", e)),
@@ -122,8 +176,10 @@ fn main() {
                             // Clone minimal runner state per thread by creating a new runner
                             let mut runner_local = TestRunner::new(&config.test, *verbose, *very_verbose, *unified_diff, project_name.clone());
                             runner_local.set_quiet(true);
+                            let prev = r.student.as_ref().and_then(|s| previous.get(s)).cloned();
+                            let (g, opts) = (&g, &opts);
                             s.spawn(move |_| {
-                                let res = runner_local.test_repo(&r, None).map(|rr| (r, rr));
+                                let res = grade_repo(&mut runner_local, g, &r, prev.as_ref(), opts).map(|rr| (r, rr));
                                 let _ = tx.send(res);
                             });
                         }
@@ -138,15 +194,7 @@ fn main() {
                                         let lbl = &repos[next_to_print].display_label;
                                         if let Some(rrp) = pending.remove(lbl) {
                                             util::print_justified(lbl, longest);
-                                            if rrp.results.is_empty() {
-                                                println!("{}", rrp.comment);
-                                            } else {
-                                                for t in &rrp.results {
-                                                    let tok = crate::util::format_pass_fail(&t.test, t.rubric, t.score);
-                                                    if t.score == t.rubric { crate::util::print_green(&tok); } else { crate::util::print_red(&tok); }
-                                                }
-                                                println!("{}", crate::testcases::TestRunner::make_earned_avail_static(&rrp.results));
-                                            }
+                                            print_summary(&rrp);
                                             next_to_print += 1;
                                         } else { break; }
                                     }
@@ -164,7 +212,7 @@ fn main() {
                         let repo_dir = std::path::PathBuf::from(format!("./{}-{}{}", project_name, stu, suffix_opt.as_deref().map(|s| format!("-{}", s)).unwrap_or_default()));
                         if repo_dir.is_dir() {
                             if let Some(h) = git::Git::get_short_hash(&repo_dir) {
-                                let url = format!("https://github.com/{}/{}-{}/tree/{}", config.git.org, project_name, stu, h);
+                                let url = git::Git::new(config.git.clone()).web_url(&project_name, stu, &h);
                                 let header = format!("Test results for repo as of this commit: {}\n\n", url);
                                 if !rr.comment.starts_with("Test results for repo as of this commit:") {
                                     rr.comment = format!("{}{}", header, rr.comment.clone());
@@ -175,24 +223,17 @@ fn main() {
                 }
                 runner.print_histogram(&only_results);
                 if let Err(e) = runner.write_class_json(&only_results, suffix_opt.as_deref()) { print_red(&format!("{}\n", e)); std::process::exit(3); }
+                if let Some(c) = &opts.cache {
+                    if let Err(e) = c.lock().unwrap().save(&cache_path) { print_red(&format!("{}: {}\n", cache_path, e)); }
+                }
             }
         }
         Commands::Exec { project, exec_cmd, students, jobs, by_date } => {
             // Build repo list from students (like pull), honoring project subdir
-            let list: Vec<String> = if let Some(list) = students { list.clone() } else { config.config.students.clone() };
-            if list.is_empty() {
-                print_red("No students provided and Config.students is empty\n");
-                std::process::exit(2);
-            }
+            let list = student_list_or_exit(&config, students);
             let project_name = project.clone().unwrap_or_else(|| util::project_from_cwd());
             let runner = TestRunner::new(&config.test, false, false, false, project_name.clone());
-            let suffix_opt: Option<String> = if *by_date {
-                let d = match dates::Dates::from_tests_path(&runner.tests_path, &project_name) {
-                    Ok(d) => d,
-                    Err(e) => { print_red(&format!("{}\n", e)); std::process::exit(2); }
-                };
-                match d.select() { Some(sel) => Some(sel.suffix.clone()), None => { return; } }
-            } else { None };
+            let Some(DateChoice { suffix: suffix_opt, .. }) = resolve_date(&config, &project_name, *by_date, &None) else { return };
             let mut repos = vec![];
             for s in list { repos.push(testcases::Repo::student(project_name.clone(), s, runner.project_subdir(), suffix_opt.clone())); }
             let longest = repos.iter().map(|r| r.display_label.len()).max().unwrap_or(0) + 1;
@@ -254,47 +295,52 @@ fn main() {
                 });
             }
         }
-        Commands::Clone { project, students, verbose, date, by_date } => {
-            let list: Vec<String> = if let Some(list) = students { list.clone() } else { config.config.students.clone() };
-            if list.is_empty() {
-                print_red("No students provided and Config.students is empty\n");
-                std::process::exit(2);
-            }
+        Commands::Clone { project, students, verbose, date, by_date, jobs, tag, tag_fallback } => {
+            let list = student_list_or_exit(&config, students);
             let g = git::Git::new(config.git.clone());
             let project_name = project.clone().unwrap_or_else(|| util::project_from_cwd());
-            let (suffix_opt, date_opt) = if *by_date {
-                let runner = TestRunner::new(&config.test, false, false, false, project_name.clone());
-                let d = match dates::Dates::from_tests_path(&runner.tests_path, &project_name) {
-                    Ok(d) => d,
-                    Err(e) => { print_red(&format!("{}\n", e)); std::process::exit(2); }
-                };
-                match d.select() { Some(sel) => (Some(sel.suffix.clone()), Some(sel.date.clone())), None => { return; } }
-            } else { (None, date.clone()) };
+            let Some(DateChoice { suffix: suffix_opt, date: date_opt, .. }) = resolve_date(&config, &project_name, *by_date, date) else { return };
 
             let mut repos = vec![];
             for s in list { repos.push(testcases::Repo::student(project_name.clone(), s, None, suffix_opt.clone())); }
-            let longest = repos.iter().map(|r| r.display_label.len()).max().unwrap_or(0) + 1;
-            for r in repos.iter() {
-                util::print_justified(&r.display_label, longest);
-                g.clone_repo(&project_name, r, date_opt.as_deref(), *verbose);
-            }
+            // Verbose git output would interleave; run single-threaded then
+            let threads = if *verbose { 1 } else { jobs.unwrap_or_else(num_cpus) };
+            let tag_opt = resolve_tag(&config, &project_name, tag, tag_fallback);
+            let outcomes = run_git_ordered(&repos, threads, |r| match &tag_opt {
+                // The date then only applies as the fallback for untagged repos
+                Some((t, fb)) => match g.clone_repo(&project_name, r, None, *verbose) {
+                    git::GitOutcome::Cloned | git::GitOutcome::AlreadyExists => g.checkout_submission(r, t, *fb, date_opt.as_deref()).0,
+                    other => other,
+                },
+                None => g.clone_repo(&project_name, r, date_opt.as_deref(), *verbose),
+            });
+            git::print_summary(&outcomes);
         }
-        Commands::Pull { project, students } => {
-            let list: Vec<String> = if let Some(list) = students { list.clone() } else { config.config.students.clone() };
-            if list.is_empty() {
-                print_red("No students provided and Config.students is empty\n");
-                std::process::exit(2);
-            }
+        Commands::Pull { project, students, jobs, tag, tag_fallback } => {
+            let list = student_list_or_exit(&config, students);
             let g = git::Git::new(config.git.clone());
             let project_name = project.clone().unwrap_or_else(|| util::project_from_cwd());
             let mut repos = vec![];
             for s in list { repos.push(testcases::Repo::student(project_name.clone(), s, None, None)); }
-            let longest = repos.iter().map(|r| r.display_label.len()).max().unwrap_or(0) + 1;
-            for r in repos.iter() {
-                util::print_justified(&r.display_label, longest);
-                g.pull_repo(r);
-                println!();
-            }
+            let threads = jobs.unwrap_or_else(num_cpus);
+            let tag_opt = resolve_tag(&config, &project_name, tag, tag_fallback);
+            let outcomes = run_git_ordered(&repos, threads, |r| match (g.pull_repo(r), &tag_opt) {
+                (o, Some((t, fb))) if o.is_ok() => g.checkout_submission(r, t, *fb, None).0,
+                (o, _) => o,
+            });
+            git::print_summary(&outcomes);
+        }
+        Commands::Checkout { project, students, date, by_date, branch, jobs } => {
+            let list = student_list_or_exit(&config, students);
+            let g = git::Git::new(config.git.clone());
+            let project_name = project.clone().unwrap_or_else(util::project_from_cwd);
+            // Existing clones are moved in place, so the dates.toml suffix is not used here
+            let Some(DateChoice { date: date_opt, .. }) = resolve_date(&config, &project_name, *by_date, date) else { return };
+            let mut repos = vec![];
+            for s in list { repos.push(testcases::Repo::student(project_name.clone(), s, None, None)); }
+            let threads = jobs.unwrap_or_else(num_cpus);
+            let outcomes = run_git_ordered(&repos, threads, |r| g.checkout_repo(r, branch.as_deref(), date_opt.as_deref()));
+            git::print_summary(&outcomes);
         }
 
         Commands::View { project, file, by_date, no_color } => {
@@ -337,25 +383,268 @@ fn main() {
             let longest = labels.iter().map(|s| s.len()).max().unwrap_or(0) + 1;
             for (rr, lbl) in results.iter().zip(labels.iter()) {
                 util::print_justified(lbl, longest);
-                if rr.results.is_empty() {
-                    println!("{}", rr.comment);
-                } else {
-                    for t in &rr.results {
-                        let tok = crate::util::format_pass_fail(&t.test, t.rubric, t.score);
-                        if t.score == t.rubric { crate::util::print_green(&tok); } else { crate::util::print_red(&tok); }
-                    }
-                    println!("{}", crate::testcases::TestRunner::make_earned_avail_static(&rr.results));
-                }
+                print_summary(rr);
             }
             runner.print_histogram(&results);
         }
-        Commands::Upload { project, file, verbose, by_date } => {
+        Commands::Upload { project, file, verbose, by_date, dry_run, confirm, undo } => {
             let project_name = project.clone().unwrap_or_else(|| util::project_from_cwd());
-            if let Err(e) = canvas::upload_class(config.canvas.clone(), config.canvas_mapper.clone(), &project_name, file.as_deref(), *verbose, *by_date) {
+            let opts = canvas::UploadOpts { verbose: *verbose, audit_log: config.config.upload_log_path(), by_date: *by_date, dry_run: *dry_run, confirm: *confirm, ..Default::default() };
+            let res = match undo {
+                Some(run_id) => canvas::undo_upload(config.canvas.clone(), run_id, &opts),
+                None => canvas::upload_class(config.canvas.clone(), config.canvas_mapper.clone(), &project_name, file.as_deref(), &opts),
+            };
+            if let Err(e) = res {
                 print_red(&format!("{}\n", e));
                 std::process::exit(64);
             }
         }
+        Commands::History { project, students, by_date } => {
+            let list = student_list_or_exit(&config, students);
+            let project_name = project.clone().unwrap_or_else(util::project_from_cwd);
+            let Some(DateChoice { suffix: suffix_opt, .. }) = resolve_date(&config, &project_name, *by_date, &None) else { return };
+            let mut rows = vec![];
+            for s in list {
+                let r = Repo::student(project_name.clone(), s.clone(), None, suffix_opt.clone());
+                match history::repo_history(&s, &r.local_path) {
+                    Ok(h) => rows.push(h),
+                    Err(e) => print_red(&format!("{}: {}\n", r.display_label, e)),
+                }
+            }
+            history::print_table(&rows);
+            let stem = match &suffix_opt { Some(suf) => format!("{}-{}-history", project_name, suf), None => format!("{}-history", project_name) };
+            for (path, res) in [(format!("{}.csv", stem), history::write_csv(&format!("{}.csv", stem), &rows)), (format!("{}.json", stem), history::write_json(&format!("{}.json", stem), &rows))] {
+                match res { Ok(()) => println!("Wrote {}", path), Err(e) => { print_red(&format!("{}: {}\n", path, e)); std::process::exit(3); } }
+            }
+        }
+        Commands::Audit { project, students, date, by_date, allow, large_lines, window_hours } => {
+            let list = student_list_or_exit(&config, students);
+            let project_name = project.clone().unwrap_or_else(util::project_from_cwd);
+            let Some(DateChoice { suffix: suffix_opt, date: date_opt, .. }) = resolve_date(&config, &project_name, *by_date, date) else { return };
+            let deadline = match date_opt.as_deref().map(dates::parse_deadline).transpose() {
+                Ok(d) => d,
+                Err(e) => { print_red(&format!("{}\n", e)); std::process::exit(2); }
+            };
+            // The mapping CSV adds each student's login as an accepted identity
+            let mapper = if config.canvas_mapper.map_path.is_empty() { None } else {
+                match canvas::CanvasMapper::from_cfg(&config.canvas_mapper) {
+                    Ok(m) => Some(m),
+                    Err(e) => { print_red(&format!("{}\n", e)); std::process::exit(2); }
+                }
+            };
+            let opts = git::AuditOpts { allow: allow.clone(), deadline, large_lines: *large_lines, window_hours: *window_hours };
+            let repos: Vec<Repo> = list.iter().map(|s| Repo::student(project_name.clone(), s.clone(), None, suffix_opt.clone())).collect();
+            let longest = repos.iter().map(|r| r.display_label.len()).max().unwrap_or(0) + 1;
+            let mut report = vec![];
+            for r in &repos {
+                let student = r.student.clone().unwrap_or_default();
+                let mut ids = vec![student.clone()];
+                ids.extend(mapper.as_ref().and_then(|m| m.lookup(&student)));
+                util::print_justified(&r.display_label, longest);
+                match git::Git::audit_repo(&r.local_path, &ids, &opts) {
+                    Ok(flags) if flags.is_empty() => print_green("OK\n"),
+                    Ok(flags) => {
+                        util::print_yellow(&format!("{} flag(s)\n", flags.len()));
+                        for f in &flags { println!("    {} {}: {}", f.commit, f.kind, f.detail); }
+                        report.push(serde_json::json!({ "student": student, "flags": flags }));
+                    }
+                    Err(e) => print_red(&format!("{}\n", e)),
+                }
+            }
+            let path = match &suffix_opt { Some(suf) => format!("{}-{}-audit.json", project_name, suf), None => format!("{}-audit.json", project_name) };
+            match serde_json::to_string_pretty(&report).map_err(anyhow::Error::from).and_then(|s| std::fs::write(&path, s).map_err(anyhow::Error::from)) {
+                Ok(()) => println!("Wrote {}", path),
+                Err(e) => { print_red(&format!("{}: {}\n", path, e)); std::process::exit(3); }
+            }
+        }
+        Commands::Roster { action: RosterCmd::Sync { project, roster: roster_opt, exclude, check } } => {
+            let project_name = project.clone().unwrap_or_else(util::project_from_cwd);
+            let mut gh = match github::Github::new(config.github.clone(), config.git.org.clone(), project_name.clone(), false) {
+                Ok(g) => g,
+                Err(e) => { print_red(&format!("GitHub client init failed: {}\n", e)); std::process::exit(2); }
+            };
+            gh.set_http_cache(config.config.http_cache_dir());
+            let discovered: Vec<String> = match gh.list_project_students() {
+                Ok(list) => list.into_iter().filter(|s| !exclude.contains(s)).collect(),
+                Err(e) => { print_red(&format!("{}\n", e)); std::process::exit(2); }
+            };
+            let path = roster_opt.as_deref().map(util::expand_tilde).unwrap_or_else(|| config.config.roster_path());
+            let existing = roster::read_roster(&path).unwrap_or_default();
+            let diff = roster::merge(&existing, &discovered);
+            println!("{} repos found for {} in {}", discovered.len(), project_name, config.git.org);
+            if !diff.missing.is_empty() { util::print_yellow(&format!("On roster without a repo: {}\n", diff.missing.join(" "))); }
+            if !diff.added.is_empty() { util::print_yellow(&format!("Repos not on roster: {}\n", diff.added.join(" "))); }
+            if !*check && !diff.added.is_empty() {
+                if let Err(e) = roster::write_roster(&path, &diff.merged) { print_red(&format!("{}: {}\n", path, e)); std::process::exit(3); }
+                println!("Added {} student(s) to {}", diff.added.len(), path);
+            }
+        }
+        Commands::Distribute { project, files, dest, branch, message, pr, title, students, jobs, by_date } => {
+            let list = student_list_or_exit(&config, students);
+            let project_name = project.clone().unwrap_or_else(util::project_from_cwd);
+            let to_copy = match distribution_files(files, dest.as_deref()) {
+                Ok(f) => f,
+                Err(e) => { print_red(&format!("{}\n", e)); std::process::exit(2); }
+            };
+            let Some(DateChoice { suffix: suffix_opt, .. }) = resolve_date(&config, &project_name, *by_date, &None) else { return };
+            let repos: Vec<Repo> = list.into_iter().map(|s| Repo::student(project_name.clone(), s, None, suffix_opt.clone())).collect();
+            let g = git::Git::new(config.git.clone());
+            let outcomes = run_git_ordered(&repos, jobs.unwrap_or_else(num_cpus), |r| g.distribute(r, &to_copy, branch.as_deref(), message));
+            if *pr {
+                let gh = match github::Github::new(config.github.clone(), config.git.org.clone(), project_name.clone(), false) {
+                    Ok(g) => g,
+                    Err(e) => { print_red(&format!("GitHub client init failed: {}\n", e)); std::process::exit(2); }
+                };
+                let title = title.clone().unwrap_or_else(|| message.clone());
+                println!("\nPull requests");
+                let longest = repos.iter().map(|r| r.display_label.len()).max().unwrap_or(0) + 1;
+                for (r, (_, o)) in repos.iter().zip(outcomes.iter()) {
+                    let git::GitOutcome::Pushed { branch: head, base, .. } = o else { continue };
+                    util::print_justified(&r.display_label, longest);
+                    match gh.open_pull_request(r.student.as_deref().unwrap_or_default(), head, base, &title, message) {
+                        Ok((true, url)) => print_green(&format!("Opened {}\n", url)),
+                        Ok((false, url)) => util::print_yellow(&format!("Already open {}\n", url)),
+                        Err(e) => print_red(&format!("{}\n", e)),
+                    }
+                }
+            }
+            git::print_summary(&outcomes);
+        }
+        Commands::Workflow { action: WorkflowCmd::Generate { project, output, tests_repo, install_from, artifact_name, check_run, force } } => {
+            let project_name = project.clone().unwrap_or_else(util::project_from_cwd);
+            let tests_repo = tests_repo.clone().unwrap_or_else(|| format!("{}/tests", config.git.org));
+            let install_from = install_from.clone().unwrap_or_else(|| format!("https://{}/{}/autograder-rust", git::Git::new(config.git.clone()).host(), config.git.org));
+            let yaml = actions::workflow_yaml(&project_name, &tests_repo, &install_from, artifact_name, *check_run);
+            if output == "-" { print!("{}", yaml); return; }
+            let path = std::path::Path::new(output);
+            if path.exists() && !*force { print_red(&format!("{} already exists; use --force to overwrite\n", output)); std::process::exit(2); }
+            if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+                if let Err(e) = std::fs::create_dir_all(dir) { print_red(&format!("{}: {}\n", dir.display(), e)); std::process::exit(3); }
+            }
+            if let Err(e) = std::fs::write(path, yaml) { print_red(&format!("{}: {}\n", output, e)); std::process::exit(3); }
+            println!("Wrote {}. Add a TESTS_TOKEN secret that can read {}, then commit it to each {}-* repo", output, tests_repo, project_name);
+        }
+        Commands::Feedback { action: FeedbackCmd::Github { project, mode, file, by_date, students, dry_run } } => {
+            let project_name = project.clone().unwrap_or_else(util::project_from_cwd);
+            let mode = match github::FeedbackMode::parse(mode) {
+                Ok(m) => m,
+                Err(e) => { print_red(&format!("{}\n", e)); std::process::exit(2); }
+            };
+            let runner = TestRunner::new(&config.test, false, false, false, project_name.clone());
+            let Some(DateChoice { suffix: suffix_opt, .. }) = resolve_date(&config, &project_name, *by_date, &None) else { return };
+            let json_path = file.clone().unwrap_or_else(|| runner.class_json_path(suffix_opt.as_deref()));
+            let data = match std::fs::read_to_string(&json_path) {
+                Ok(s) => s,
+                Err(e) => { print_red(&format!("{} does not exist. Run \"grade-rs class -p {}\" first ({})\n", json_path, project_name, e)); std::process::exit(2); }
+            };
+            let results: Vec<testcases::RepoResult> = match serde_json::from_str(&data) {
+                Ok(v) => v,
+                Err(e) => { print_red(&format!("Failed to parse {}: {}\n", json_path, e)); std::process::exit(2); }
+            };
+            let mut gh = match github::Github::new(config.github.clone(), config.git.org.clone(), project_name.clone(), false) {
+                Ok(g) => g,
+                Err(e) => { print_red(&format!("GitHub client init failed: {}\n", e)); std::process::exit(2); }
+            };
+            gh.set_http_cache(config.config.http_cache_dir());
+            let results: Vec<testcases::RepoResult> = results.into_iter()
+                .filter(|rr| rr.student.as_ref().map(|s| students.as_ref().map(|l| l.contains(s)).unwrap_or(true)).unwrap_or(false))
+                .collect();
+            let repos: Vec<Repo> = results.iter().map(|rr| Repo::student(project_name.clone(), rr.student.clone().unwrap_or_default(), None, suffix_opt.clone())).collect();
+            let longest = repos.iter().map(|r| r.display_label.len()).max().unwrap_or(0) + 1;
+            let mut failed = false;
+            for (rr, repo) in results.iter().zip(repos.iter()) {
+                util::print_justified(&repo.display_label, longest);
+                // The commit that was graded, else the clone's HEAD for results saved before it was recorded
+                let commit = rr.commit.clone().or_else(|| git::Git::get_head_hash(&repo.local_path));
+                let body = github::feedback_body(&project_name, rr);
+                match gh.post_feedback(repo.student.as_deref().unwrap_or_default(), mode, &body, commit.as_deref(), *dry_run) {
+                    Ok((action, url)) => {
+                        let verb = match (&action, *dry_run) {
+                            (github::FeedbackAction::Unchanged, _) => "unchanged",
+                            (github::FeedbackAction::Create, false) => "created",
+                            (github::FeedbackAction::Create, true) => "would create",
+                            (github::FeedbackAction::Update, false) => "updated",
+                            (github::FeedbackAction::Update, true) => "would update",
+                        };
+                        println!("{}{}", verb, url.map(|u| format!(" {}", u)).unwrap_or_default());
+                    }
+                    Err(e) => { failed = true; print_red(&format!("{}\n", e)); }
+                }
+            }
+            if *dry_run {
+                if let Some(rr) = results.first() { println!("\nExample body:\n{}", github::feedback_body(&project_name, rr)); }
+            }
+            if failed { std::process::exit(1); }
+        }
+        Commands::Actions { action: ActionsCmd::Rerun { project, students, mode, workflow, branch, wait, timeout, jobs } } => {
+            let list = student_list_or_exit(&config, students);
+            let project_name = project.clone().unwrap_or_else(util::project_from_cwd);
+            let mode = match github::RerunMode::parse(mode) {
+                Ok(m) => m,
+                Err(e) => { print_red(&format!("{}\n", e)); std::process::exit(2); }
+            };
+            let gh = match github::Github::new(config.github.clone(), config.git.org.clone(), project_name.clone(), false) {
+                Ok(g) => g,
+                Err(e) => { print_red(&format!("GitHub client init failed: {}\n", e)); std::process::exit(2); }
+            };
+            let deadline = std::time::Instant::now() + std::time::Duration::from_secs(*timeout);
+            let repos: Vec<Repo> = list.into_iter().map(|s| Repo::student(project_name.clone(), s, None, None)).collect();
+            let outcomes = run_ordered(&repos, jobs.unwrap_or(8), |r| {
+                let student = r.student.as_deref().unwrap_or_default();
+                let t = gh.rerun(student, mode, workflow.as_deref(), branch.as_deref())?;
+                let run = if *wait { gh.wait_for_run(student, &t, deadline, RUN_POLL)? } else { None };
+                Ok::<_, anyhow::Error>((t, run))
+            }, |o| match o {
+                Ok((github::Triggered::Attempt { attempt, .. }, None)) if !*wait => print_green(&format!("Re-running, attempt {}\n", attempt + 1)),
+                Ok((github::Triggered::Dispatch { workflow, .. }, None)) if !*wait => print_green(&format!("Dispatched {}\n", workflow)),
+                Ok((_, None)) => util::print_yellow("Run did not start in time\n"),
+                Ok((_, Some(run))) if !run.is_completed() => util::print_yellow(&format!("Still {} {}\n", run.status, run.html_url)),
+                Ok((_, Some(run))) => {
+                    let conclusion = run.conclusion.as_deref().unwrap_or("unknown");
+                    let line = format!("{} {}\n", conclusion, run.html_url);
+                    if conclusion == "success" { print_green(&line) } else { print_red(&line) }
+                }
+                Err(e) => print_red(&format!("{}\n", e)),
+            });
+            let ok = |o: &anyhow::Result<(github::Triggered, Option<github::ActionRun>)>| match o {
+                Ok((_, run)) => !*wait || run.as_ref().map(|r| r.conclusion.as_deref() == Some("success")).unwrap_or(false),
+                Err(_) => false,
+            };
+            if !outcomes.iter().all(|(_, o)| ok(o)) { std::process::exit(1); }
+        }
+        Commands::Archive { action: Some(ArchiveCmd::Verify { file }), .. } => {
+            match archive::verify_archive(std::path::Path::new(file)) {
+                Ok((manifest, problems)) => {
+                    println!("{}: {} repos for {}", file, manifest.repos.len(), manifest.project);
+                    if !problems.is_empty() {
+                        for p in &problems { print_red(&format!("{}\n", p)); }
+                        std::process::exit(1);
+                    }
+                    print_green("All repos match the manifest\n");
+                }
+                Err(e) => { print_red(&format!("{}\n", e)); std::process::exit(2); }
+            }
+        }
+        Commands::Archive { action: None, project, students, by_date, output } => {
+            let list = student_list_or_exit(&config, students);
+            let project_name = project.clone().unwrap_or_else(util::project_from_cwd);
+            let runner = TestRunner::new(&config.test, false, false, false, project_name.clone());
+            let Some(DateChoice { suffix: suffix_opt, .. }) = resolve_date(&config, &project_name, *by_date, &None) else { return };
+            // Scores come from the class JSON when it exists
+            let scores: std::collections::HashMap<String, i64> = std::fs::read_to_string(runner.class_json_path(suffix_opt.as_deref())).ok()
+                .and_then(|s| serde_json::from_str::<Vec<testcases::RepoResult>>(&s).ok())
+                .map(|v| v.into_iter().filter_map(|rr| rr.student.map(|s| (s, rr.score))).collect())
+                .unwrap_or_default();
+            let repos: Vec<(String, std::path::PathBuf)> = list.into_iter()
+                .map(|s| { let r = Repo::student(project_name.clone(), s.clone(), None, suffix_opt.clone()); (s, r.local_path) })
+                .collect();
+            let out = output.clone().unwrap_or_else(|| match &suffix_opt { Some(suf) => format!("{}-{}-archive.zip", project_name, suf), None => format!("{}-archive.zip", project_name) });
+            let tests_toml = std::path::Path::new(&runner.tests_path).join(&project_name).join(format!("{}.toml", project_name));
+            match archive::create_archive(std::path::Path::new(&out), &project_name, suffix_opt.as_deref(), &repos, &scores, &tests_toml) {
+                Ok(m) => println!("Wrote {} ({} repos)", out, m.repos.len()),
+                Err(e) => { print_red(&format!("{}: {}\n", out, e)); std::process::exit(3); }
+            }
+        }
         Commands::Rollup { project, by_date: _ } => {
                 let project_name = project.clone().unwrap_or_else(|| util::project_from_cwd());
                 let runner = TestRunner::new(&config.test, false, false, false, project_name.clone());
@@ -367,4 +656,204 @@ fn main() {
     print_green("\nDone\n");
 }
 
+// Publish results as a check run on the commit being built; the repo, commit and token come from Actions
+fn publish_check_run(config: &Config, project: &str, rr: &testcases::RepoResult, build_output: Option<&str>, subdir: Option<String>) -> anyhow::Result<String> {
+    let env = |k: &str| std::env::var(k).ok().filter(|v| !v.is_empty());
+    let (Some(repository), Some(sha)) = (env("GITHUB_REPOSITORY"), env("GITHUB_SHA")) else {
+        anyhow::bail!("GITHUB_REPOSITORY and GITHUB_SHA are not set; --check-run only works inside GitHub Actions")
+    };
+    let cfg = crate::config::GithubCfg {
+        host_name: env("GITHUB_API_URL").unwrap_or_else(|| config.github.host_name.clone()),
+        access_token: env("GITHUB_TOKEN").unwrap_or_else(|| config.github.access_token.clone()),
+    };
+    // GITHUB_WORKFLOW_REF is <owner>/<name>/.github/workflows/grade.yml@refs/heads/main
+    let workflow = env("GITHUB_WORKFLOW_REF")
+        .and_then(|r| r.split('@').next().and_then(|p| p.strip_prefix(&format!("{}/", repository))).map(String::from))
+        .unwrap_or_else(|| String::from(".github/workflows/grade.yml"));
+    let (conclusion, output) = actions::check_run(project, rr, build_output, subdir.as_deref(), &workflow);
+    let owner = repository.split('/').next().unwrap_or_default().to_string();
+    let gh = github::Github::new(cfg, owner, project.to_string(), false)?;
+    gh.create_check_run(&repository, &sha, "grade-rs", conclusion, &output)
+}
+
+// How often to check on workflow runs while waiting for them
+const RUN_POLL: std::time::Duration = std::time::Duration::from_secs(15);
+
+// Students from -s, else Config.students or the roster file; exits when there are none
+fn student_list_or_exit(config: &Config, students: &Option<Vec<String>>) -> Vec<String> {
+    let list = students.clone().unwrap_or_else(|| config.config.student_list());
+    if list.is_empty() {
+        print_red("No students provided, Config.students is empty, and no roster file was found\n");
+        std::process::exit(2);
+    }
+    list
+}
+
+// The deadline a command works against: with -d, the dates.toml entry picked for the project
+// (its repo suffix, date and late policy), otherwise just the --date given
+struct DateChoice {
+    suffix: Option<String>,
+    date: Option<String>,
+    late_policy: Option<dates::LatePolicy>,
+}
+
+// None when the pick is cancelled
+fn resolve_date(config: &Config, project: &str, by_date: bool, date: &Option<String>) -> Option<DateChoice> {
+    if !by_date { return Some(DateChoice { suffix: None, date: date.clone(), late_policy: None }); }
+    let runner = TestRunner::new(&config.test, false, false, false, project.to_string());
+    let d = match dates::Dates::from_tests_path(&runner.tests_path, project) {
+        Ok(d) => d,
+        Err(e) => { print_red(&format!("{}\n", e)); std::process::exit(2); }
+    };
+    let sel = d.select()?;
+    Some(DateChoice { suffix: Some(sel.suffix.clone()), date: Some(sel.date.clone()), late_policy: d.late_policy.clone() })
+}
+
+// Submission tag from --tag or the suite's [project] submission_tag, with its fallback policy
+fn resolve_tag(config: &Config, project: &str, tag: &Option<String>, fallback: &Option<String>) -> Option<(String, git::TagFallback)> {
+    let mut runner = TestRunner::new(&config.test, false, false, false, project.to_string());
+    // A missing tests repo only matters when the tag has to come from it
+    let (cfg_tag, cfg_fallback) = runner.submission_cfg().unwrap_or((None, String::from("deadline")));
+    let tag = tag.clone().or(cfg_tag)?;
+    match git::TagFallback::parse(fallback.as_deref().unwrap_or(&cfg_fallback)) {
+        Ok(fb) => Some((tag, fb)),
+        Err(e) => { print_red(&format!("{}\n", e)); std::process::exit(2); }
+    }
+}
+
+// Per-class settings applied around each repo's test run
+struct GradeOpts {
+    tag: Option<(String, git::TagFallback)>,
+    date: Option<String>,
+    late_policy: Option<dates::LatePolicy>,
+    cache: Option<std::sync::Mutex<cache::ResultCache>>,
+}
+
+// Check out the submission when tags are in use, test (or re-test previous failures),
+// then deduct any late penalty
+fn grade_repo(runner: &mut TestRunner, g: &git::Git, r: &Repo, prev: Option<&testcases::RepoResult>, opts: &GradeOpts) -> anyhow::Result<testcases::RepoResult> {
+    let mut submission = None;
+    if let Some((t, fb)) = &opts.tag {
+        let (outcome, sub) = g.checkout_submission(r, t, *fb, opts.date.as_deref());
+        if sub.is_none() {
+            let comment = match outcome {
+                git::GitOutcome::NotSubmitted => format!("Not submitted: tag {} not found", t),
+                other => other.to_string(),
+            };
+            return Ok(testcases::RepoResult { comment, results: vec![], score: 0, student: r.student.clone(), build_err: None, submission: None, late: None, commit: None });
+        }
+        submission = sub;
+    }
+    let head = opts.cache.as_ref().and_then(|_| git::Git::get_head_hash(&r.local_path));
+    let cached = match (&opts.cache, &head, &r.student) {
+        (Some(c), Some(h), Some(s)) => c.lock().unwrap().get(s, h).cloned(),
+        _ => None,
+    };
+    let mut rr = match (cached, prev) {
+        (Some(c), _) => c,
+        (None, Some(p)) => runner.retest_failures(r, p)?,
+        (None, None) => runner.test_repo(r, None)?,
+    };
+    // Only the test outcome itself is cached or penalized below; a cache entry written by an
+    // older version may still carry a penalty
+    if rr.late.is_some() { rr = runner.without_late(&rr); }
+    if let (Some(c), Some(h), Some(s)) = (&opts.cache, &head, &r.student) { c.lock().unwrap().insert(s, h, &rr); }
+    rr.submission = submission;
+    if let (Some(policy), Some(date), Some(student)) = (&opts.late_policy, &opts.date, &r.student) {
+        let deadline = dates::parse_deadline(date)?;
+        if let Ok(hours) = git::Git::hours_late(&r.local_path, deadline) {
+            if let Some((percent, explanation)) = policy.penalty(student, hours) {
+                let points = (rr.score as f64 * percent / 100.0).round() as i64;
+                rr.score -= points;
+                rr.comment = format!("{} ({} points)\n{}", explanation, -points, rr.comment);
+                rr.late = Some(testcases::LatePenalty { hours_late: hours, percent, points });
+            }
+        }
+    }
+    Ok(rr)
+}
+
+// (source, path in repo) for each file to distribute; directories are copied recursively.
+// Relative paths keep their place in the repo, others go to its root, and --dest puts everything under one directory.
+fn distribution_files(files: &[String], dest: Option<&str>) -> anyhow::Result<Vec<(std::path::PathBuf, String)>> {
+    let mut out = vec![];
+    for f in files {
+        let path = std::path::Path::new(f);
+        if !path.exists() { anyhow::bail!("{} does not exist", f); }
+        let name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+        let keeps_place = path.is_relative() && path.components().all(|c| matches!(c, std::path::Component::Normal(_) | std::path::Component::CurDir));
+        let rel = match dest {
+            Some(d) => format!("{}/{}", d.trim_end_matches('/'), name),
+            None if keeps_place => path.components().filter(|c| matches!(c, std::path::Component::Normal(_))).map(|c| c.as_os_str().to_string_lossy().to_string()).collect::<Vec<_>>().join("/"),
+            None => name,
+        };
+        if path.is_dir() {
+            for inner in files::list_files(path) {
+                let to = if rel.is_empty() { inner.clone() } else { format!("{}/{}", rel, inner) };
+                out.push((path.join(&inner), to));
+            }
+        } else {
+            out.push((path.to_path_buf(), rel));
+        }
+    }
+    Ok(out)
+}
+
+// One line per repo: colored per-test tokens and earned/available, or the comment when nothing ran
+fn print_summary(rr: &testcases::RepoResult) {
+    if rr.results.is_empty() { println!("{}", rr.comment); return; }
+    for t in &rr.results {
+        let tok = util::format_pass_fail(&t.test, t.rubric, t.score);
+        if t.score == t.rubric { util::print_green(&tok); } else { print_red(&tok); }
+    }
+    println!("{}", TestRunner::make_earned_avail_static(&rr.results));
+}
+
+// Run a git operation on each repo with -j workers, printing outcomes in input order as they finish
+fn run_git_ordered<F>(repos: &[Repo], threads: usize, op: F) -> Vec<(String, git::GitOutcome)>
+where F: Fn(&Repo) -> git::GitOutcome + Sync {
+    run_ordered(repos, threads, op, |o| o.print())
+}
+
+// Run op on each repo with -j workers; print each result in input order as soon as its turn comes
+fn run_ordered<T, F, P>(repos: &[Repo], threads: usize, op: F, print: P) -> Vec<(String, T)>
+where T: Send, F: Fn(&Repo) -> T + Sync, P: Fn(&T) + Sync {
+    let longest = repos.iter().map(|r| r.display_label.len()).max().unwrap_or(0) + 1;
+    let mut outcomes = vec![];
+    if threads == 1 {
+        for r in repos {
+            util::print_justified(&r.display_label, longest);
+            let o = op(r);
+            print(&o);
+            outcomes.push((r.display_label.clone(), o));
+        }
+        return outcomes;
+    }
+    let pool = rayon::ThreadPoolBuilder::new().num_threads(threads).build().unwrap();
+    let mut next_to_print: usize = 0;
+    let mut pending: std::collections::HashMap<String, T> = std::collections::HashMap::new();
+    pool.scope(|s| {
+        let (tx, rx) = crossbeam_channel::unbounded();
+        for r in repos {
+            let tx = tx.clone();
+            let op = &op;
+            s.spawn(move |_| { let _ = tx.send((r.display_label.clone(), op(r))); });
+        }
+        drop(tx);
+        for (lbl, o) in rx.iter() {
+            pending.insert(lbl, o);
+            while next_to_print < repos.len() {
+                let elbl = &repos[next_to_print].display_label;
+                if let Some(o) = pending.remove(elbl) {
+                    util::print_justified(elbl, longest);
+                    print(&o);
+                    outcomes.push((elbl.clone(), o));
+                    next_to_print += 1;
+                } else { break; }
+            }
+        }
+    });
+    outcomes
+}
+
 fn num_cpus() -> usize { std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1) }
@@ -0,0 +1,38 @@
+use std::fs;
+
+// Roster file: one student (GitHub ID) per line; blank lines and '#' comments are ignored
+pub fn read_roster(path: &str) -> anyhow::Result<Vec<String>> {
+    let content = fs::read_to_string(path)?;
+    Ok(content.lines()
+        .map(|l| l.split('#').next().unwrap_or("").trim())
+        .filter(|l| !l.is_empty())
+        .map(|l| l.to_string())
+        .collect())
+}
+
+pub fn write_roster(path: &str, students: &[String]) -> anyhow::Result<()> {
+    let mut out = String::new();
+    for s in students { out.push_str(s); out.push('\n'); }
+    fs::write(path, out)?;
+    Ok(())
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RosterDiff {
+    pub merged: Vec<String>,
+    // Repos whose owner was not on the roster
+    pub added: Vec<String>,
+    // Roster students without a repo
+    pub missing: Vec<String>,
+}
+
+// Keep the roster's order and append newly discovered students in sorted order
+pub fn merge(roster: &[String], discovered: &[String]) -> RosterDiff {
+    let mut added: Vec<String> = discovered.iter().filter(|s| !roster.contains(s)).cloned().collect();
+    added.sort();
+    added.dedup();
+    let missing = roster.iter().filter(|s| !discovered.contains(s)).cloned().collect();
+    let mut merged = roster.to_vec();
+    merged.extend(added.iter().cloned());
+    RosterDiff { merged, added, missing }
+}
@@ -1,10 +1,15 @@
 use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs;
 use std::path::{Path, PathBuf};
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use crate::cmd::{exec_capture, ExecOptions};
 use crate::config::TestCfg;
+use crate::files::{check_files, list_files, sha256_file, FilesCfg};
+use crate::harness::{parse_junit, parse_tap, score_cases};
+use crate::history::commit_count;
 use crate::util::{expand_tilde, format_pass_fail, normalize_lines, print_green, print_red, print_yellow};
 
 #[derive(Debug, Deserialize, Clone)]
@@ -14,26 +19,127 @@ pub struct ProjectCfg {
     #[serde(default)] pub subdir: Option<String>,
     #[serde(default = "default_timeout")] pub timeout: u64,
     #[serde(default = "default_capture_stderr")] pub capture_stderr: bool,
+    #[serde(default)] pub files: Option<FilesCfg>,
+    // Reruns allowed for a failing test before it counts as failed; tests may override
+    #[serde(default)] pub retries: u32,
+    // Tag students create to mark their submission, e.g. "project1-final"
+    #[serde(default)] pub submission_tag: Option<String>,
+    // When the tag is missing: "deadline" (last commit before the date) or "not-submitted"
+    #[serde(default = "default_tag_fallback")] pub tag_fallback: String,
+    // Commits required for the "commits" result, worth min_commits_rubric; 0 disables it
+    #[serde(default)] pub min_commits: usize,
+    #[serde(default)] pub min_commits_rubric: i64,
 }
 fn default_build() -> String { "make".into() }
 fn default_timeout() -> u64 { 60 }
 fn default_capture_stderr() -> bool { true }
+fn default_tag_fallback() -> String { "deadline".into() }
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct TestCaseCfg {
     #[serde(default)] pub case_sensitive: bool,
-    pub expected: String,
+    #[serde(default)] pub expected: String,
     pub input: Vec<String>,
     pub name: String,
     #[serde(default = "default_output")] pub output: String,
     #[serde(default)] pub rubric: i64,
+    // "output" compares against expected; "junit" and "tap" parse a harness report from output
+    #[serde(default = "default_kind")] pub kind: String,
+    // Harness case name -> points; when empty the rubric is split equally across cases
+    #[serde(default)] pub points: BTreeMap<String, i64>,
+    // Performance tiers awarded after the output is correct, from the median of `repeat` runs
+    #[serde(default)] pub time_limits: Vec<TimeLimit>,
+    #[serde(default = "default_repeat")] pub repeat: u32,
+    #[serde(default)] pub retries: Option<u32>,
+    // Seconds; overrides [project] timeout
+    #[serde(default)] pub timeout: Option<u64>,
 }
 fn default_output() -> String { "stdout".into() }
+fn default_kind() -> String { "output".into() }
+fn default_repeat() -> u32 { 1 }
+
+// Unit name used by retest_failures for the [project.files] rules
+const FILES_UNIT: &str = ":files";
+const COMMITS_UNIT: &str = "commits";
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct TimeLimit {
+    pub under_ms: u64,
+    pub points: i64,
+}
+
+impl TestCaseCfg {
+    pub fn time_points(&self) -> i64 { self.time_limits.iter().map(|t| t.points).max().unwrap_or(0) }
+
+    pub fn total_points(&self) -> i64 {
+        let base = if self.points.is_empty() { self.rubric } else { self.points.values().sum() };
+        base + self.time_points()
+    }
+}
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct ProjectToml {
+    // Other suite files, relative to this one; their tests come first
+    #[serde(default)] pub include: Vec<String>,
+    // Fields every [[tests]] entry in this file (and its includes) inherits unless set
+    #[serde(default)] pub defaults: toml::Table,
     #[serde(default)] pub project: Option<ProjectCfg>,
-    #[serde(default)] pub tests: Vec<TestCaseCfg>,
+    #[serde(default)] pub tests: Vec<toml::Table>,
+}
+
+// Accumulated state while resolving a suite and its includes
+#[derive(Default)]
+struct Suite {
+    project: Option<ProjectCfg>,
+    tests: Vec<TestCaseCfg>,
+    origins: HashMap<String, PathBuf>,
+    // Every suite file read, in load order
+    files: Vec<PathBuf>,
+}
+
+fn load_suite(path: &Path, inherited: &toml::Table, stack: &mut Vec<PathBuf>, suite: &mut Suite) -> anyhow::Result<()> {
+    let canon = fs::canonicalize(path).map_err(|e| anyhow::anyhow!("File not found: {} ({})", path.display(), e))?;
+    if stack.contains(&canon) {
+        let chain: Vec<String> = stack.iter().chain(std::iter::once(&canon)).map(|p| p.display().to_string()).collect();
+        anyhow::bail!("Include cycle: {}", chain.join(" -> "));
+    }
+    // Reached again through another include (a diamond): its tests are already loaded
+    if suite.files.contains(&canon) { return Ok(()); }
+    let content = fs::read_to_string(&canon).map_err(|e| anyhow::anyhow!("File not found: {} ({})", path.display(), e))?;
+    let doc: ProjectToml = toml::from_str(&content).map_err(|e| anyhow::anyhow!("Failed to parse {}: {}", path.display(), e))?;
+    suite.files.push(canon.clone());
+
+    let mut defaults = inherited.clone();
+    for (k, v) in doc.defaults {
+        if k == "name" { anyhow::bail!("{}: [defaults] cannot set a test name", path.display()); }
+        defaults.insert(k, v);
+    }
+
+    stack.push(canon.clone());
+    let dir = canon.parent().map(|p| p.to_path_buf()).unwrap_or_default();
+    for inc in &doc.include {
+        load_suite(&dir.join(inc), &defaults, stack, suite)?;
+    }
+    stack.pop();
+
+    // The including file's [project] wins over its includes
+    if doc.project.is_some() { suite.project = doc.project; }
+    for mut table in doc.tests {
+        for (k, v) in &defaults {
+            if !table.contains_key(k) { table.insert(k.clone(), v.clone()); }
+        }
+        let tc: TestCaseCfg = toml::Value::Table(table).try_into().map_err(|e| anyhow::anyhow!("Failed to parse {}: {}", path.display(), e))?;
+        // Only output tests are timed
+        if tc.kind != "output" && !tc.time_limits.is_empty() {
+            anyhow::bail!("{}: test \"{}\" of kind \"{}\" cannot have time_limits", path.display(), tc.name, tc.kind);
+        }
+        if let Some(first) = suite.origins.get(&tc.name) {
+            anyhow::bail!("Duplicate test name \"{}\" in {} (first defined in {})", tc.name, path.display(), first.display());
+        }
+        suite.origins.insert(tc.name.clone(), canon.clone());
+        suite.tests.push(tc);
+    }
+    Ok(())
 }
 
 #[derive(Debug, Clone)]
@@ -60,12 +166,16 @@ impl Repo {
     }
 }
 
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
 pub struct TcResult {
     pub rubric: i64,
     pub score: i64,
     pub test: String,
     #[serde(skip_serializing_if = "Option::is_none")] pub test_err: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")] pub duration_ms: Option<u64>,
+    // Set when retries were needed: total attempts, and whether the last one passed
+    #[serde(default, skip_serializing_if = "Option::is_none")] pub attempts: Option<u32>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")] pub flaky: bool,
 }
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
@@ -75,6 +185,26 @@ pub struct RepoResult {
     pub score: i64,
     #[serde(skip_serializing_if = "Option::is_none")] pub student: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")] pub build_err: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")] pub submission: Option<Submission>,
+    #[serde(default, skip_serializing_if = "Option::is_none")] pub late: Option<LatePenalty>,
+    // Full SHA of the commit that was tested
+    #[serde(default, skip_serializing_if = "Option::is_none")] pub commit: Option<String>,
+}
+
+// Deduction applied to score for a submission after the deadline
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct LatePenalty {
+    pub hours_late: f64,
+    pub percent: f64,
+    pub points: i64,
+}
+
+// Commit that was graded; tag is None when the submission tag was missing and the fallback was used
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Submission {
+    #[serde(skip_serializing_if = "Option::is_none")] pub tag: Option<String>,
+    pub hash: String,
+    pub date: String,
 }
 
 pub struct TestRunner {
@@ -87,25 +217,52 @@ pub struct TestRunner {
     project: String,
     project_cfg: ProjectCfg,
     testcases: Vec<TestCaseCfg>,
+    suite_files: Vec<PathBuf>,
+    // make output from the last failed build, for compiler annotations
+    build_output: Option<String>,
 }
 
 impl TestRunner {
     pub fn new(cfg: &TestCfg, verbose: bool, very_verbose: bool, unified_diff: bool, project: String) -> Self {
         let tests_path = expand_tilde(&cfg.tests_path);
         let digital_path = expand_tilde(&cfg.digital_path);
-        TestRunner { tests_path, digital_path, verbose, very_verbose, unified_diff, quiet: false, project, project_cfg: ProjectCfg { build: default_build(), strip_output: None, subdir: None, timeout: default_timeout(), capture_stderr: default_capture_stderr() }, testcases: vec![] }
+        TestRunner { tests_path, digital_path, verbose, very_verbose, unified_diff, quiet: false, project, project_cfg: ProjectCfg { build: default_build(), strip_output: None, subdir: None, timeout: default_timeout(), capture_stderr: default_capture_stderr(), files: None, retries: 0, submission_tag: None, tag_fallback: default_tag_fallback(), min_commits: 0, min_commits_rubric: 0 }, testcases: vec![], suite_files: vec![], build_output: None }
     }
 
     pub fn set_quiet(&mut self, quiet: bool) { self.quiet = quiet; }
 
     pub fn project_subdir(&self) -> Option<String> { self.project_cfg.subdir.clone() }
 
+    pub fn build_output(&self) -> Option<&str> { self.build_output.as_deref() }
+
+    // Hash of the suite files (including ones pulled in from outside the project
+    // directory) and everything in the project's tests directory
+    pub fn tests_fingerprint(&mut self) -> anyhow::Result<String> {
+        self.load_testcases()?;
+        let tests_dir = Path::new(&self.tests_path).join(&self.project);
+        let mut hasher = Sha256::new();
+        for f in &self.suite_files {
+            hasher.update(format!("{} {}\n", f.display(), sha256_file(f)?));
+        }
+        for rel in list_files(&tests_dir) {
+            hasher.update(format!("{} {}\n", rel, sha256_file(&tests_dir.join(&rel))?));
+        }
+        Ok(hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect())
+    }
+
+    // Submission tag and fallback policy from the suite's [project] table
+    pub fn submission_cfg(&mut self) -> anyhow::Result<(Option<String>, String)> {
+        self.load_testcases()?;
+        Ok((self.project_cfg.submission_tag.clone(), self.project_cfg.tag_fallback.clone()))
+    }
+
     fn load_testcases(&mut self) -> anyhow::Result<()> {
         let path = Path::new(&self.tests_path).join(&self.project).join(format!("{}.toml", &self.project));
-        let content = fs::read_to_string(&path).map_err(|e| anyhow::anyhow!("File not found: {} ({})", path.display(), e))?;
-        let doc: ProjectToml = toml::from_str(&content).map_err(|e| anyhow::anyhow!("Failed to parse {}: {}", path.display(), e))?;
-        if let Some(pcfg) = doc.project { self.project_cfg = pcfg; }
-        self.testcases = doc.tests;
+        let mut suite = Suite::default();
+        load_suite(&path, &toml::Table::new(), &mut vec![], &mut suite)?;
+        if let Some(pcfg) = suite.project { self.project_cfg = pcfg; }
+        self.testcases = suite.tests;
+        self.suite_files = suite.files;
         if self.testcases.is_empty() {
             print_yellow(&format!("No test cases found: {}\n", path.display()));
         }
@@ -121,7 +278,8 @@ impl TestRunner {
         out
     }
 
-    fn build(&self, repo: &Repo) -> Option<String> {
+    fn build(&mut self, repo: &Repo) -> Option<String> {
+        self.build_output = None;
         match self.project_cfg.build.as_str() {
             "none" => None,
             "make" => {
@@ -133,7 +291,7 @@ impl TestRunner {
                 let opts = ExecOptions { cwd: None, timeout: Duration::from_secs(30), capture_stderr: true, output_limit: 220_000 };
                 match crate::cmd::exec_capture_with_status(&cmd, &opts) {
                     Ok((_out, true, _)) => None,
-                    Ok((_out, false, _)) => Some("Program did not make successfully".into()),
+                    Ok((out, false, _)) => { self.build_output = Some(out); Some("Program did not make successfully".into()) }
                     Err(_) => Some("Program did not make successfully".into()),
                 }
             }
@@ -142,12 +300,13 @@ impl TestRunner {
     }
 
     fn run_one_test(&self, repo: &Repo, tc: &TestCaseCfg) -> TcResult {
-        let mut result = TcResult { rubric: tc.rubric, score: 0, test: tc.name.clone(), test_err: None };
-        let timeout = Duration::from_secs(self.project_cfg.timeout);
+        let mut result = TcResult { rubric: tc.rubric, score: 0, test: tc.name.clone(), test_err: None, ..Default::default() };
+        let timeout = self.timeout_for(tc);
         let opts = ExecOptions { cwd: Some(repo.local_path.to_string_lossy().to_string()), timeout, capture_stderr: self.project_cfg.capture_stderr, output_limit: 220_000 };
 
         let mut cmdline: Vec<String> = vec![];
         for i in tc.input.iter() { cmdline.push(self.interpolate(i, &tc.name)); }
+        let started = Instant::now();
         let actual_res = if tc.output == "stdout" {
             match crate::cmd::exec_capture_with_status(&cmdline, &opts) {
                 Ok((out, true, _)) => Ok(out),
@@ -163,6 +322,7 @@ impl TestRunner {
             let f = repo.local_path.join(&tc.output);
             fs::read_to_string(&f).map_err(|e| crate::cmd::ExecError::Io(e))
         };
+        if !tc.time_limits.is_empty() { result.duration_ms = Some(started.elapsed().as_millis() as u64); }
 
         match actual_res {
             Ok(mut actual) => {
@@ -170,29 +330,103 @@ impl TestRunner {
                 if self.match_expected(&tc, &actual) { result.score = tc.rubric; }
                 self.print_verbose(&tc, &cmdline, &actual);
             }
-            Err(e) => {
-                let msg = match e {
-                    crate::cmd::ExecError::Timeout(_) => "Program timed out (infinite loop?)".to_string(),
-                    crate::cmd::ExecError::OutputLimit(_) => "Program produced too much output (infinite loop?)".to_string(),
-                    crate::cmd::ExecError::Io(ref ioe) if ioe.raw_os_error() == Some(8) => {
-                        let exe = cmdline.get(0).cloned().unwrap_or_else(|| "./program".into());
-                        format!("OSError: [Errno 8] Exec format error: '{}'", exe)
-                    }
-                    crate::cmd::ExecError::Io(ref ioe) if ioe.kind() == std::io::ErrorKind::NotFound =>
-                        "Program not found (build failed?)".to_string(),
-                    crate::cmd::ExecError::Io(ioe) => format!("IO error: {}", ioe),
-                };
-                result.test_err = Some(msg);
-            }
+            Err(e) => result.test_err = Some(Self::exec_error_message(e, &cmdline)),
         }
+        result
+    }
+
+    fn timeout_for(&self, tc: &TestCaseCfg) -> Duration {
+        Duration::from_secs(tc.timeout.unwrap_or(self.project_cfg.timeout))
+    }
 
-        if !self.quiet {
-            let fmt = format_pass_fail(&result.test, result.rubric, result.score);
-            if result.score == 0 { print_red(&format!("{}\n", fmt)); } else { print_green(&format!("{}\n", fmt)); }
+    // Re-run a test command only to measure it; None if it fails or times out
+    fn time_command(&self, repo: &Repo, tc: &TestCaseCfg) -> Option<u64> {
+        let opts = ExecOptions { cwd: Some(repo.local_path.to_string_lossy().to_string()), timeout: self.timeout_for(tc), capture_stderr: self.project_cfg.capture_stderr, output_limit: 220_000 };
+        let cmdline: Vec<String> = tc.input.iter().map(|i| self.interpolate(i, &tc.name)).collect();
+        let started = Instant::now();
+        exec_capture(&cmdline, &opts).ok()?;
+        Some(started.elapsed().as_millis() as u64)
+    }
+
+    // Award the best time tier the median run satisfies, once the output is correct
+    fn score_timing(&self, repo: &Repo, tc: &TestCaseCfg, first: &TcResult) -> TcResult {
+        let mut result = TcResult { rubric: tc.time_points(), score: 0, test: format!("{}/time", tc.name), ..Default::default() };
+        if first.test_err.is_some() || first.score != first.rubric {
+            result.test_err = Some("Not timed: output incorrect".into());
+            return result;
+        }
+        let mut samples = vec![first.duration_ms.unwrap_or(0)];
+        for _ in 1..tc.repeat.max(1) {
+            match self.time_command(repo, tc) {
+                Some(ms) => samples.push(ms),
+                None => { result.test_err = Some("Program failed during timing runs".into()); return result; }
+            }
+        }
+        samples.sort_unstable();
+        let median = samples[samples.len() / 2];
+        result.duration_ms = Some(median);
+        result.score = tc.time_limits.iter().filter(|t| median < t.under_ms).map(|t| t.points).max().unwrap_or(0);
+        if result.score < result.rubric {
+            let best = tc.time_limits.iter().filter(|t| t.points == result.rubric).map(|t| t.under_ms).min().unwrap_or(0);
+            result.test_err = Some(format!("Median {} ms (full credit under {} ms)", median, best));
+        }
+        if self.verbose {
+            println!("\n\n===[{}]===timing\n{} run(s): {:?} ms, median {} ms", tc.name, samples.len(), samples, median);
         }
         result
     }
 
+    fn exec_error_message(e: crate::cmd::ExecError, cmdline: &[String]) -> String {
+        match e {
+            crate::cmd::ExecError::Timeout(_) => "Program timed out (infinite loop?)".to_string(),
+            crate::cmd::ExecError::OutputLimit(_) => "Program produced too much output (infinite loop?)".to_string(),
+            crate::cmd::ExecError::Io(ref ioe) if ioe.raw_os_error() == Some(8) => {
+                let exe = cmdline.first().cloned().unwrap_or_else(|| "./program".into());
+                format!("OSError: [Errno 8] Exec format error: '{}'", exe)
+            }
+            crate::cmd::ExecError::Io(ref ioe) if ioe.kind() == std::io::ErrorKind::NotFound =>
+                "Program not found (build failed?)".to_string(),
+            crate::cmd::ExecError::Io(ioe) => format!("IO error: {}", ioe),
+        }
+    }
+
+    // Run a unit-test harness and score each case it reports
+    fn run_harness_test(&self, repo: &Repo, tc: &TestCaseCfg) -> Vec<TcResult> {
+        let timeout = self.timeout_for(tc);
+        let opts = ExecOptions { cwd: Some(repo.local_path.to_string_lossy().to_string()), timeout, capture_stderr: self.project_cfg.capture_stderr, output_limit: 220_000 };
+        let cmdline: Vec<String> = tc.input.iter().map(|i| self.interpolate(i, &tc.name)).collect();
+        let fail = |msg: String| vec![TcResult { rubric: tc.total_points(), score: 0, test: tc.name.clone(), test_err: Some(msg), ..Default::default() }];
+
+        let report = if tc.output == "stdout" {
+            match exec_capture(&cmdline, &opts) { Ok(out) => out, Err(e) => return fail(Self::exec_error_message(e, &cmdline)) }
+        } else {
+            // Remove any stale report so only this run's results are graded
+            let f = repo.local_path.join(self.interpolate(&tc.output, &tc.name));
+            let _ = fs::remove_file(&f);
+            if let Err(e) = exec_capture(&cmdline, &opts) { return fail(Self::exec_error_message(e, &cmdline)); }
+            match fs::read_to_string(&f) { Ok(s) => s, Err(_) => return fail(format!("Test report not found: {}", tc.output)) }
+        };
+        let cases = if tc.kind == "junit" {
+            match parse_junit(&report) { Ok(c) => c, Err(e) => return fail(e.to_string()) }
+        } else { parse_tap(&report) };
+        if cases.is_empty() { return fail("No test cases found in report".into()); }
+        if self.verbose {
+            for c in cases.iter().filter(|c| !c.passed) {
+                println!("\n\n===[{}/{}]===failure\n{}", tc.name, c.name, c.message.as_deref().unwrap_or(""));
+            }
+        }
+        score_cases(&tc.name, &cases, &tc.points, tc.rubric)
+    }
+
+    fn print_result(&self, result: &TcResult) {
+        if self.quiet { return; }
+        let fmt = format_pass_fail(&result.test, result.rubric, result.score);
+        if result.rubric == 0 && result.test_err.is_some() {
+            // Warning-only rule: no points at stake
+            print_yellow(&format!("{}   {}\n", fmt, result.test_err.as_deref().unwrap_or("")));
+        } else if result.score == 0 { print_red(&format!("{}\n", fmt)); } else { print_green(&format!("{}\n", fmt)); }
+    }
+
     fn match_expected(&self, tc: &TestCaseCfg, actual: &str) -> bool {
         let exp = self.interpolate(&tc.expected, &tc.name);
         let lhs = normalize_lines(&exp.trim_end(), tc.case_sensitive);
@@ -232,6 +466,16 @@ impl TestRunner {
     }
 
     fn make_comment(&self, repo_result: &RepoResult) -> String {
+        Self::format_comment(repo_result, &self.make_earned_avail(repo_result))
+    }
+
+    // Comment for results produced elsewhere (e.g. Actions artifacts); available points come from the results
+    pub fn make_comment_static(repo_result: &RepoResult) -> String {
+        let avail: i64 = repo_result.results.iter().map(|t| t.rubric).sum();
+        Self::format_comment(repo_result, &format!("{}/{}", repo_result.score, avail))
+    }
+
+    fn format_comment(repo_result: &RepoResult, earned: &str) -> String {
         // Match Python formatting of comment body
         let mut out = String::new();
         let mut pass_concat = String::new();
@@ -261,14 +505,13 @@ impl TestRunner {
                 pass_concat.push_str(&label);
             }
         }
-        let earned = self.make_earned_avail(repo_result);
         if !pass_concat.is_empty() {
             out.push_str(&prefix);
             out.push_str(&pass_concat);
-            out.push_str(&earned);
+            out.push_str(earned);
         } else {
             if out.is_empty() { out.push_str(&prefix); }
-            out.push_str(&earned);
+            out.push_str(earned);
         }
         out
     }
@@ -288,21 +531,133 @@ impl TestRunner {
         if !repo.local_path.is_dir() {
             let msg = format!("Local repo {} does not exist", repo.local_path.display());
             if !self.quiet { print_red(&format!("{}\n", msg)); }
-            return Ok(RepoResult { comment: msg, results: vec![], score: 0, student: repo.student.clone(), build_err: None });
+            return Ok(RepoResult { comment: msg, results: vec![], score: 0, student: repo.student.clone(), build_err: None, submission: None, late: None, commit: None });
         }
 
         let build_err = self.build(repo);
         let mut results = vec![];
         let iter = self.testcases.iter().filter(|tc| only_name.map(|n| n == tc.name).unwrap_or(true));
-        for tc in iter { results.push(self.run_one_test(repo, tc)); }
+        for tc in iter { results.extend(self.run_unit(repo, tc)); }
+        for r in self.run_file_checks(repo)?.into_iter().chain(self.check_commits(repo)) {
+            if only_name.map(|n| n == r.test).unwrap_or(true) { self.print_result(&r); results.push(r); }
+        }
+        Ok(self.finish_repo_result(repo, results, build_err))
+    }
+
+    // Re-run only the tests that failed in a previous result, keeping passing results as they were
+    pub fn retest_failures(&mut self, repo: &Repo, previous: &RepoResult) -> anyhow::Result<RepoResult> {
+        self.load_testcases()?;
+        if previous.results.is_empty() || !repo.local_path.is_dir() { return self.test_repo(repo, None); }
+        let failed: HashSet<String> = previous.results.iter()
+            .filter(|r| r.score != r.rubric)
+            .filter_map(|r| self.unit_of(&r.test))
+            .collect();
+        if failed.is_empty() { return Ok(self.without_late(previous)); }
+
+        let build_err = self.build(repo);
+        let mut results = vec![];
+        let mut rerun: HashSet<String> = HashSet::new();
+        for r in &previous.results {
+            match self.unit_of(&r.test) {
+                Some(unit) if failed.contains(&unit) => {
+                    if !rerun.insert(unit.clone()) { continue; }
+                    if unit == FILES_UNIT {
+                        let checks = self.run_file_checks(repo)?;
+                        for c in &checks { self.print_result(c); }
+                        results.extend(checks);
+                    } else if unit == COMMITS_UNIT {
+                        if let Some(c) = self.check_commits(repo) { self.print_result(&c); results.push(c); }
+                    } else if let Some(tc) = self.testcases.iter().find(|tc| tc.name == unit) {
+                        results.extend(self.run_unit(repo, tc));
+                    }
+                }
+                _ => results.push(r.clone()),
+            }
+        }
+        Ok(self.finish_repo_result(repo, results, build_err))
+    }
+
+    // A graded result as the tests left it: any late penalty is given back and the comment
+    // rebuilt, so the penalty can be recomputed from the current policy
+    pub fn without_late(&self, rr: &RepoResult) -> RepoResult {
+        let mut rr = rr.clone();
+        if let Some(late) = rr.late.take() { rr.score += late.points; }
+        rr.comment = self.make_comment(&rr);
+        rr
+    }
+
+    // Name of the test case (or file rule set) that produced a result
+    fn unit_of(&self, result_name: &str) -> Option<String> {
+        if let Some(tc) = self.testcases.iter().find(|tc| result_name == tc.name || result_name.starts_with(&format!("{}/", tc.name))) {
+            return Some(tc.name.clone());
+        }
+        let rule = result_name.split(':').next().unwrap_or("");
+        if result_name.contains(':') && ["required", "forbidden", "pattern", "protected"].contains(&rule) { return Some(FILES_UNIT.into()); }
+        if result_name == COMMITS_UNIT { return Some(COMMITS_UNIT.into()); }
+        None
+    }
+
+    // Run one test case, rerunning it up to `retries` times while it fails
+    fn run_unit(&self, repo: &Repo, tc: &TestCaseCfg) -> Vec<TcResult> {
+        let retries = tc.retries.unwrap_or(self.project_cfg.retries);
+        let mut attempt = 1;
+        let mut results = self.run_unit_once(repo, tc);
+        while attempt <= retries && results.iter().any(|r| r.score != r.rubric) {
+            attempt += 1;
+            results = self.run_unit_once(repo, tc);
+        }
+        if attempt > 1 {
+            let passed = results.iter().all(|r| r.score == r.rubric);
+            for r in &mut results { r.attempts = Some(attempt); r.flaky = passed; }
+            if passed && self.verbose { print_yellow(&format!("{} is flaky: passed on attempt {}\n", tc.name, attempt)); }
+        }
+        if tc.kind == "output" && !tc.time_limits.is_empty() {
+            let timed = self.score_timing(repo, tc, &results[0]);
+            results.push(timed);
+        }
+        for r in &results { self.print_result(r); }
+        results
+    }
+
+    fn run_unit_once(&self, repo: &Repo, tc: &TestCaseCfg) -> Vec<TcResult> {
+        match tc.kind.as_str() {
+            "junit" | "tap" => self.run_harness_test(repo, tc),
+            "output" => vec![self.run_one_test(repo, tc)],
+            other => vec![TcResult { rubric: tc.total_points(), score: 0, test: tc.name.clone(), test_err: Some(format!("Unknown test kind: \"{}\"", other)), ..Default::default() }],
+        }
+    }
+
+    fn run_file_checks(&self, repo: &Repo) -> anyhow::Result<Vec<TcResult>> {
+        let Some(fcfg) = &self.project_cfg.files else { return Ok(vec![]) };
+        let tests_dir = Path::new(&self.tests_path).join(&self.project);
+        check_files(fcfg, &repo.local_path, &tests_dir)
+    }
+
+    fn check_commits(&self, repo: &Repo) -> Option<TcResult> {
+        let min = self.project_cfg.min_commits;
+        if min == 0 { return None; }
+        let rubric = self.project_cfg.min_commits_rubric;
+        let test = COMMITS_UNIT.to_string();
+        Some(match commit_count(&repo.local_path) {
+            Ok(n) if n >= min => TcResult { rubric, score: rubric, test, test_err: None, ..Default::default() },
+            Ok(n) => TcResult { rubric, score: 0, test, test_err: Some(format!("{} commits, at least {} required", n, min)), ..Default::default() },
+            Err(e) => TcResult { rubric, score: 0, test, test_err: Some(e.to_string()), ..Default::default() },
+        })
+    }
+
+    fn finish_repo_result(&self, repo: &Repo, results: Vec<TcResult>, build_err: Option<String>) -> RepoResult {
         let score = results.iter().map(|r| r.score).sum();
-        let mut repo_result = RepoResult { comment: String::new(), results, score, student: repo.student.clone(), build_err };
+        let mut repo_result = RepoResult { comment: String::new(), results, score, student: repo.student.clone(), build_err, submission: None, late: None, commit: crate::git::Git::get_head_hash(&repo.local_path) };
         repo_result.comment = self.make_comment(&repo_result);
         if !self.quiet { println!("{}", self.make_earned_avail(&repo_result)); }
-        Ok(repo_result)
+        repo_result
     }
 
-    pub fn total_rubric(&self) -> i64 { self.testcases.iter().map(|tc| tc.rubric).sum() }
+    pub fn total_rubric(&self) -> i64 {
+        let files = self.project_cfg.files.as_ref().map(|f| f.total_rubric()).unwrap_or(0);
+        let commits = if self.project_cfg.min_commits > 0 { self.project_cfg.min_commits_rubric } else { 0 };
+        self.testcases.iter().map(|tc| tc.total_points()).sum::<i64>() + files + commits
+    }
 
     pub fn print_histogram(&self, class_results: &[RepoResult]) {
         // Derive available points from any non-empty result set
@@ -319,8 +674,12 @@ impl TestRunner {
         for (score, freq) in items { let pct = (freq as f64) / (class_results.len() as f64) * 100.0; println!("{}/{}: {}  ({:.1}%)", score, avail, freq, pct); }
     }
 
+    pub fn class_json_path(&self, suffix: Option<&str>) -> String {
+        if let Some(s) = suffix { format!("{}-{}.json", self.project, s) } else { format!("{}.json", self.project) }
+    }
+
     pub fn write_class_json(&self, class_results: &[RepoResult], suffix: Option<&str>) -> anyhow::Result<()> {
-        let fname = if let Some(s) = suffix { format!("{}-{}.json", self.project, s) } else { format!("{}.json", self.project) };
+        let fname = self.class_json_path(suffix);
         let data = serde_json::to_string_pretty(class_results)?;
         fs::write(&fname, data)?;
         Ok(())
@@ -0,0 +1,83 @@
+use autograder_rust::actions::{check_run, compiler_annotations, step_summary, ArtifactResults};
+use autograder_rust::testcases::{RepoResult, TcResult};
+
+fn repo_result(results: Vec<TcResult>, build_err: Option<String>) -> RepoResult {
+    let score = results.iter().map(|t| t.score).sum();
+    RepoResult { comment: String::new(), results, score, student: None, build_err, submission: None, late: None, commit: None }
+}
+
+#[test]
+fn artifact_round_trip() {
+    let rr = repo_result(vec![
+        TcResult { test: "01".into(), rubric: 10, score: 10, ..Default::default() },
+        TcResult { test: "02".into(), rubric: 5, score: 0, test_err: Some("Program timed out".into()), ..Default::default() },
+    ], None);
+    let json = serde_json::to_string(&ArtifactResults::from_repo_result(&rr)).unwrap();
+    assert!(!json.contains("build_err"));
+
+    let parsed: ArtifactResults = serde_json::from_str(&json).unwrap();
+    let mut back = repo_result(vec![], None);
+    parsed.apply(&mut back);
+    assert_eq!(back.score, 10);
+    assert_eq!(back.results.len(), 2);
+    assert_eq!((back.results[1].test.as_str(), back.results[1].rubric, back.results[1].score), ("02", 5, 0));
+    assert_eq!(back.results[1].test_err.as_deref(), Some("Program timed out"));
+
+    // The grade-only format still yields a score
+    let mut old = repo_result(vec![], None);
+    serde_json::from_str::<ArtifactResults>("{\"grade\": 7.6}").unwrap().apply(&mut old);
+    assert_eq!(old.score, 8);
+    assert!(old.results.is_empty());
+}
+
+#[test]
+fn step_summary_markdown() {
+    let rr = repo_result(vec![
+        TcResult { test: "01".into(), rubric: 10, score: 10, ..Default::default() },
+        TcResult { test: "02".into(), rubric: 5, score: 0, test_err: Some("a|b\nc".into()), ..Default::default() },
+    ], Some("Program did not make successfully".into()));
+    let md = step_summary("projx", &rr);
+    assert!(md.starts_with("## projx results: 10/15\n\n**Program did not make successfully**\n\n| Test |"));
+    assert!(md.contains("| ✅ 01 | 10/10 |  |\n"));
+    assert!(md.contains("| ❌ 02 | 0/5 | a\\|b c |\n"));
+}
+
+#[test]
+fn compiler_output_annotations() {
+    let out = "make: Entering directory '/work/projx'\n\
+        gcc -c -o main.o main.c\n\
+        ./main.c:12:5: error: 'x' undeclared (first use in this function)\n\
+        ./main.c:12:5: error: 'x' undeclared (first use in this function)\n\
+        util.h:3:1: warning: data definition has no type\n\
+        add.s: Assembler messages:\n\
+        add.s:7: Error: bad instruction `mvo r0,#1'\n\
+        /usr/include/stdio.h:20:1: error: outside the repo\n\
+        make: *** [Makefile:5: main.o] Error 1\n";
+    let a = compiler_annotations(out, Some("projx"));
+    let got: Vec<(&str, u32, &str, &str)> = a.iter().map(|a| (a.path.as_str(), a.start_line, a.annotation_level.as_str(), a.message.as_str())).collect();
+    assert_eq!(got, vec![
+        ("projx/main.c", 12, "failure", "'x' undeclared (first use in this function)"),
+        ("projx/util.h", 3, "warning", "data definition has no type"),
+        ("projx/add.s", 7, "failure", "bad instruction `mvo r0,#1'"),
+    ]);
+}
+
+#[test]
+fn check_run_conclusion_and_test_annotations() {
+    let rr = repo_result(vec![
+        TcResult { test: "01".into(), rubric: 10, score: 10, ..Default::default() },
+        TcResult { test: "02".into(), rubric: 5, score: 0, test_err: Some("Program timed out".into()), ..Default::default() },
+    ], None);
+    let (conclusion, output) = check_run("projx", &rr, None, None, ".github/workflows/grade.yml");
+    assert_eq!(conclusion, "failure");
+    assert_eq!(output.title, "10/15 points");
+    assert_eq!(output.summary, step_summary("projx", &rr));
+    assert_eq!(output.annotations.len(), 1);
+    let a = &output.annotations[0];
+    assert_eq!((a.path.as_str(), a.title.as_deref(), a.message.as_str()), (".github/workflows/grade.yml", Some("02 (0/5)"), "Program timed out"));
+
+    let rr = repo_result(vec![TcResult { test: "01".into(), rubric: 10, score: 10, ..Default::default() }], None);
+    let (conclusion, output) = check_run("projx", &rr, None, None, ".github/workflows/grade.yml");
+    assert_eq!(conclusion, "success");
+    assert!(output.annotations.is_empty());
+}
@@ -0,0 +1,61 @@
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{Read, Write};
+
+use autograder_rust::archive::{create_archive, verify_archive};
+
+mod common;
+use common::git;
+
+#[test]
+fn archive_packs_committed_files_and_verifies() {
+    let tmp = tempfile::tempdir().unwrap();
+    let base = tmp.path();
+    let repo = base.join("projx-alice");
+    fs::create_dir_all(repo.join("src")).unwrap();
+    fs::write(repo.join("src/main.c"), "int main() {}\n").unwrap();
+    fs::write(repo.join("Makefile"), "all:\n").unwrap();
+    git(&["init", "-q", "-b", "main"], &repo);
+    git(&["add", "."], &repo);
+    git(&["commit", "-q", "-m", "start"], &repo);
+    // Build product and uncommitted edit are not part of the graded commit
+    fs::write(repo.join("projx"), "binary").unwrap();
+    fs::write(repo.join("Makefile"), "changed\n").unwrap();
+    let toml = base.join("projx.toml");
+    fs::write(&toml, "[project]\n").unwrap();
+
+    let out = base.join("out.zip");
+    let repos = vec![("alice".to_string(), repo.clone()), ("bob".to_string(), base.join("projx-bob"))];
+    let scores = HashMap::from([("alice".to_string(), 7)]);
+    let m = create_archive(&out, "projx", None, &repos, &scores, &toml).unwrap();
+    assert_eq!(m.repos.len(), 1);
+    assert_eq!(m.repos[0].files, 2);
+    assert_eq!(m.repos[0].score, Some(7));
+    assert_eq!(m.repos[0].commit.len(), 40);
+    assert!(m.tests_sha256.is_some());
+
+    let mut zip = zip::ZipArchive::new(File::open(&out).unwrap()).unwrap();
+    let mut makefile = String::new();
+    zip.by_name("projx-alice/Makefile").unwrap().read_to_string(&mut makefile).unwrap();
+    assert_eq!(makefile, "all:\n");
+    assert!(zip.by_name("projx-alice/projx").is_err());
+
+    let (_, problems) = verify_archive(&out).unwrap();
+    assert!(problems.is_empty(), "{:?}", problems);
+
+    // Rewrite the archive with one file altered
+    let tampered = base.join("tampered.zip");
+    let mut w = zip::ZipWriter::new(File::create(&tampered).unwrap());
+    for i in 0..zip.len() {
+        let mut f = zip.by_index(i).unwrap();
+        let name = f.name().to_string();
+        let mut data = vec![];
+        f.read_to_end(&mut data).unwrap();
+        if name == "projx-alice/src/main.c" { data = b"int main() { return 1; }\n".to_vec(); }
+        w.start_file(name, zip::write::FileOptions::default()).unwrap();
+        w.write_all(&data).unwrap();
+    }
+    w.finish().unwrap();
+    let (_, problems) = verify_archive(&tampered).unwrap();
+    assert_eq!(problems, vec!["alice: contents do not match manifest checksum"]);
+}
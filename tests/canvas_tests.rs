@@ -1,8 +1,9 @@
 use std::fs;
 use std::io::Write;
 
-use autograder_rust::canvas::upload_class;
+use autograder_rust::canvas::{read_audit_log, undo_upload, upload_class, UploadOpts};
 use autograder_rust::config::{CanvasCfg, CanvasMapperCfg};
+use autograder_rust::http::HttpOpts;
 use httpmock::prelude::*;
 
 #[test]
@@ -60,7 +61,7 @@ fn canvas_upload_mock() {
 
     let canvas = CanvasCfg { host_name: host, access_token: String::from("tok"), course_name: String::from("Course X") };
     let mapper = CanvasMapperCfg { map_path: csv_path.to_string_lossy().to_string(), github_col_name: String::from("GitHub"), login_col_name: String::from("SIS Login ID") };
-    upload_class(canvas, mapper, project, Some(json_path.to_str().unwrap()), false, false).unwrap();
+    upload_class(canvas, mapper, project, Some(json_path.to_str().unwrap()), &UploadOpts::default()).unwrap();
 
     courses.assert();
     assignments.assert();
@@ -103,7 +104,9 @@ fn canvas_pagination_and_error() {
     // Call get_course_id through upload_class minimal path: expect failure due to missing CSV
     // but ensure pagination was hit
     // We'll directly construct client and call get_course_id instead for precision
-    let client = autograder_rust::canvas::CanvasClient::new(canvas, false).unwrap();
+    // Server errors are retried, so keep the backoff short
+    let opts = HttpOpts { retries: 2, backoff: std::time::Duration::from_millis(10), ..Default::default() };
+    let client = autograder_rust::canvas::CanvasClient::with_http_opts(canvas, false, opts).unwrap();
     let course_id = client.get_course_id().unwrap();
     assert_eq!(course_id, 42);
     courses_p1.assert(); courses_p2.assert();
@@ -112,5 +115,194 @@ fn canvas_pagination_and_error() {
     let err = client.get_assignment_id(course_id, "projx").unwrap_err();
     let msg = format!("{}", err);
     assert!(msg.contains("assignments GET failed"));
-    assignments_500.assert();
+    assignments_500.assert_hits(3);
+}
+
+// Course 42, assignment 7, alice (user 101, score 8) and bob (user 102, score 9) enrolled
+fn mock_course(server: &MockServer) {
+    mock_roster(server);
+    server.mock(|when, then| {
+        when.method(GET).path("/api/v1/courses/42/assignments/7/submissions/101");
+        then.status(200).header("content-type", "application/json").body("{\"score\": 8.0}");
+    });
+    server.mock(|when, then| {
+        when.method(GET).path("/api/v1/courses/42/assignments/7/submissions/102");
+        then.status(200).header("content-type", "application/json").body("{\"score\": 9.0}");
+    });
+}
+
+// Course, assignment, and enrollments for a123 (user 101) and b456 (user 102)
+fn mock_roster(server: &MockServer) {
+    server.mock(|when, then| {
+        when.method(GET).path("/api/v1/courses");
+        then.status(200).header("content-type", "application/json").body("[{\"id\":42,\"name\":\"Course X\"}]");
+    });
+    server.mock(|when, then| {
+        when.method(GET).path("/api/v1/courses/42/assignments");
+        then.status(200).header("content-type", "application/json").body("[{\"id\":7,\"name\":\"projx\"}]");
+    });
+    server.mock(|when, then| {
+        when.method(GET).path("/api/v1/courses/42/enrollments");
+        then.status(200).header("content-type", "application/json")
+            .body("[{\"user_id\":101,\"user\":{\"login_id\":\"a123\"}},{\"user_id\":102,\"user\":{\"login_id\":\"b456\"}}]");
+    });
+}
+
+#[test]
+fn canvas_upload_dry_run_writes_nothing() {
+    let server = MockServer::start();
+    mock_course(&server);
+    let put = server.mock(|when, then| {
+        when.method(PUT).path_contains("/submissions/");
+        then.status(200);
+    });
+
+    let tmp = tempfile::tempdir().unwrap();
+    let json_path = tmp.path().join("projx.json");
+    fs::write(&json_path, r#"[
+  {"student":"alice", "score": 8, "comment": "ok"},
+  {"student":"bob",   "score": 10, "comment": "great"},
+  {"student":"carol", "score": 7, "comment": "dropped"},
+  {"student":"dave",  "score": 5, "comment": "no row"}
+]"#).unwrap();
+    let csv_path = tmp.path().join("map.csv");
+    fs::write(&csv_path, "GitHub,SIS Login ID\nalice,a123\nbob,b456\ncarol,c789\n").unwrap();
+
+    let canvas = CanvasCfg { host_name: server.base_url(), access_token: String::from("tok"), course_name: String::from("Course X") };
+    let mapper = CanvasMapperCfg { map_path: csv_path.to_string_lossy().to_string(), github_col_name: String::from("GitHub"), login_col_name: String::from("SIS Login ID") };
+    let opts = UploadOpts { dry_run: true, ..Default::default() };
+    upload_class(canvas, mapper, "projx", Some(json_path.to_str().unwrap()), &opts).unwrap();
+    put.assert_hits(0);
+}
+
+#[test]
+fn canvas_upload_audit_log_and_undo() {
+    let server = MockServer::start();
+    server.mock(|when, then| {
+        when.method(GET).path("/api/v1/courses");
+        then.status(200).header("content-type", "application/json").body("[{\"id\":42,\"name\":\"Course X\"}]");
+    });
+    server.mock(|when, then| {
+        when.method(GET).path("/api/v1/courses/42/assignments");
+        then.status(200).header("content-type", "application/json").body("[{\"id\":7,\"name\":\"projx\"}]");
+    });
+    server.mock(|when, then| {
+        when.method(GET).path("/api/v1/courses/42/enrollments");
+        then.status(200).header("content-type", "application/json").body("[{\"user_id\":102,\"user\":{\"login_id\":\"b456\"}}]");
+    });
+    let mut before = server.mock(|when, then| {
+        when.method(GET).path("/api/v1/courses/42/assignments/7/submissions/102");
+        then.status(200).header("content-type", "application/json").body("{\"score\": 9.0}");
+    });
+    let upload = server.mock(|when, then| {
+        when.method(PUT).path("/api/v1/courses/42/assignments/7/submissions/102").body_contains("submission%5Bposted_grade%5D=10");
+        then.status(200);
+    });
+
+    let tmp = tempfile::tempdir().unwrap();
+    let json_path = tmp.path().join("projx.json");
+    fs::write(&json_path, r#"[{"student":"bob", "score": 10, "comment": "great"}]"#).unwrap();
+    let csv_path = tmp.path().join("map.csv");
+    fs::write(&csv_path, "GitHub,SIS Login ID\nbob,b456\n").unwrap();
+    let log = tmp.path().join("audit/uploads.jsonl");
+    let canvas = CanvasCfg { host_name: server.base_url(), access_token: String::from("tok"), course_name: String::from("Course X") };
+    let mapper = CanvasMapperCfg { map_path: csv_path.to_string_lossy().to_string(), github_col_name: String::from("GitHub"), login_col_name: String::from("SIS Login ID") };
+    let opts = UploadOpts { audit_log: Some(log.clone()), ..Default::default() };
+    upload_class(canvas.clone(), mapper, "projx", Some(json_path.to_str().unwrap()), &opts).unwrap();
+    upload.assert();
+
+    let entries = read_audit_log(&log).unwrap();
+    assert_eq!(entries.len(), 1);
+    let e = &entries[0];
+    assert_eq!((e.course_id, e.assignment_id, e.user_id, e.old_score, e.new_score, e.ok), (42, 7, 102, Some(9.0), Some(10.0), true));
+    assert_eq!(e.course, "Course X");
+    assert_eq!(e.comment_sha256.as_deref(), Some(sha256_hex("great").as_str()));
+
+    // Undo puts back 9, but only while Canvas still shows what the upload wrote
+    before.delete();
+    server.mock(|when, then| {
+        when.method(GET).path("/api/v1/courses/42/assignments/7/submissions/102");
+        then.status(200).header("content-type", "application/json").body("{\"score\": 10.0}");
+    });
+    let restore = server.mock(|when, then| {
+        when.method(PUT).path("/api/v1/courses/42/assignments/7/submissions/102").body("submission%5Bposted_grade%5D=9");
+        then.status(200);
+    });
+    undo_upload(canvas.clone(), &e.run_id, &UploadOpts { dry_run: true, ..opts.clone() }).unwrap();
+    restore.assert_hits(0);
+    undo_upload(canvas.clone(), &e.run_id, &opts).unwrap();
+    restore.assert();
+
+    let entries = read_audit_log(&log).unwrap();
+    assert_eq!(entries.len(), 2);
+    let u = &entries[1];
+    assert_ne!(u.run_id, e.run_id);
+    assert_eq!(u.undo_of.as_deref(), Some(e.run_id.as_str()));
+    assert_eq!((u.old_score, u.new_score, u.comment_sha256.is_none()), (Some(10.0), Some(9.0), true));
+    assert!(undo_upload(canvas, "19990101-000000", &opts).is_err());
+}
+
+#[test]
+fn canvas_upload_skips_failed_score_lookup_and_undo_leaves_it() {
+    let server = MockServer::start();
+    mock_roster(&server);
+    let mut alice_before = server.mock(|when, then| {
+        when.method(GET).path("/api/v1/courses/42/assignments/7/submissions/101");
+        then.status(500);
+    });
+    let mut bob_before = server.mock(|when, then| {
+        when.method(GET).path("/api/v1/courses/42/assignments/7/submissions/102");
+        then.status(200).header("content-type", "application/json").body("{\"score\": 9.0}");
+    });
+    let alice_put = server.mock(|when, then| {
+        when.method(PUT).path("/api/v1/courses/42/assignments/7/submissions/101");
+        then.status(200);
+    });
+    let mut bob_put = server.mock(|when, then| {
+        when.method(PUT).path("/api/v1/courses/42/assignments/7/submissions/102");
+        then.status(200);
+    });
+
+    let tmp = tempfile::tempdir().unwrap();
+    let json_path = tmp.path().join("projx.json");
+    fs::write(&json_path, r#"[{"student":"alice", "score": 8, "comment": "ok"}, {"student":"bob", "score": 10, "comment": "great"}]"#).unwrap();
+    let csv_path = tmp.path().join("map.csv");
+    fs::write(&csv_path, "GitHub,SIS Login ID\nalice,a123\nbob,b456\n").unwrap();
+    let log = tmp.path().join("uploads.jsonl");
+    let canvas = CanvasCfg { host_name: server.base_url(), access_token: String::from("tok"), course_name: String::from("Course X") };
+    let mapper = CanvasMapperCfg { map_path: csv_path.to_string_lossy().to_string(), github_col_name: String::from("GitHub"), login_col_name: String::from("SIS Login ID") };
+    let http = HttpOpts { retries: 1, backoff: std::time::Duration::from_millis(10), ..Default::default() };
+    let opts = UploadOpts { audit_log: Some(log.clone()), http, ..Default::default() };
+    upload_class(canvas.clone(), mapper, "projx", Some(json_path.to_str().unwrap()), &opts).unwrap();
+
+    // alice's score could not be read, so nothing was written or logged for her
+    alice_put.assert_hits(0);
+    bob_put.assert();
+    let entries = read_audit_log(&log).unwrap();
+    assert_eq!(entries.iter().map(|e| e.user_id).collect::<Vec<_>>(), vec![102]);
+
+    // With Canvas showing the uploaded scores, undo restores bob and leaves alice's grade alone
+    alice_before.delete();
+    bob_before.delete();
+    bob_put.delete();
+    server.mock(|when, then| {
+        when.method(GET).path("/api/v1/courses/42/assignments/7/submissions/101");
+        then.status(200).header("content-type", "application/json").body("{\"score\": 8.0}");
+    });
+    server.mock(|when, then| {
+        when.method(GET).path("/api/v1/courses/42/assignments/7/submissions/102");
+        then.status(200).header("content-type", "application/json").body("{\"score\": 10.0}");
+    });
+    let restore = server.mock(|when, then| {
+        when.method(PUT).path("/api/v1/courses/42/assignments/7/submissions/102").body("submission%5Bposted_grade%5D=9");
+        then.status(200);
+    });
+    undo_upload(canvas, &entries[0].run_id, &opts).unwrap();
+    restore.assert();
+    alice_put.assert_hits(0);
+}
+
+fn sha256_hex(s: &str) -> String {
+    use sha2::Digest;
+    sha2::Sha256::digest(s.as_bytes()).iter().map(|b| format!("{:02x}", b)).collect()
 }
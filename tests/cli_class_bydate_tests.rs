@@ -1,10 +1,14 @@
 use std::fs;
 use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
 use std::process::Command;
 
 use assert_cmd::prelude::*;
 use predicates::str::contains as p_contains;
 
+mod common;
+use common::{commit_file_at, git};
+
 fn write_student_repo(base: &std::path::Path, label: &str, project: &str) {
     let repo = base.join(label);
     fs::create_dir_all(&repo).unwrap();
@@ -72,3 +76,93 @@ fn cli_class_by_date_unified_diff_and_json() {
     let data = fs::read_to_string(&json_path).unwrap();
     assert!(data.contains("\"student\": \"alice\""));
 }
+
+// alice's only commit is pushed 12 hours after the deadline. Returns the config dir and dates.toml.
+fn write_late_class(base: &Path) -> (PathBuf, PathBuf) {
+    let tests_repo = write_tests_repo_with_case(base, "projx");
+    let cfgdir = base.join("cfg");
+    fs::create_dir_all(&cfgdir).unwrap();
+    fs::write(cfgdir.join("config.toml"), format!("[Test]\ntests_path = \"{}\"\n[Config]\nstudents = [\"alice\"]\n", tests_repo.to_string_lossy())).unwrap();
+
+    // Lateness is measured on the remote branch, so the student repo is a clone of a pushed, late commit
+    let work = base.join("work");
+    write_student_repo(base, "work", "projx");
+    fs::write(work.join("projx"), "#!/bin/sh\necho OK\n").unwrap();
+    git(&["init", "-q", "-b", "main"], &work);
+    git(&["add", "Makefile"], &work);
+    commit_file_at(&work, "projx", "#!/bin/sh\necho OK\n", "2025-03-11 12:00:00");
+    let bare = base.join("projx-alice.git");
+    git(&["clone", "-q", "--bare", work.to_str().unwrap(), bare.to_str().unwrap()], base);
+    git(&["clone", "-q", bare.to_str().unwrap(), "projx-alice-due"], base);
+    (cfgdir, tests_repo.join("dates.toml"))
+}
+
+// Replace the [projx.late_policy] table in dates.toml
+fn set_late_policy(dates: &Path, policy: &str) {
+    let toml = fs::read_to_string(dates).unwrap();
+    let kept = toml.split("\n[projx.late_policy]").next().unwrap().to_string();
+    fs::write(dates, format!("{}\n[projx.late_policy]\n{}\n", kept, policy)).unwrap();
+}
+
+// Runs class -d for projx and returns the class JSON
+fn run_late_class(base: &Path, cfgdir: &Path, extra: &[&str]) -> String {
+    let mut cmd = Command::cargo_bin("grade-rs").unwrap();
+    cmd.arg("class").args(["-p", "projx", "-d"]).args(extra)
+        .env("HOME", base)
+        .env("GRADE_CONFIG_DIR", cfgdir)
+        .current_dir(base);
+    cmd.assert().success();
+    fs::read_to_string(base.join("projx-due.json")).unwrap()
+}
+
+#[test]
+fn cli_class_rerun_failures_recomputes_late_penalty() {
+    let tmp = tempfile::tempdir().unwrap();
+    let base = tmp.path();
+    let (cfgdir, dates) = write_late_class(base);
+    set_late_policy(&dates, "percent_per_day = 20");
+    let first = run_late_class(base, &cfgdir, &[]);
+    assert!(first.contains("\"score\": 8"), "{}", first);
+
+    // Nothing failed, so the previous result is reused and must not be penalized again
+    let again = run_late_class(base, &cfgdir, &["--rerun-failures"]);
+    assert!(again.contains("\"score\": 8"), "{}", again);
+    assert_eq!(again.matches("Late ").count(), 1, "{}", again);
+    assert_eq!(again.matches("Test results for repo as of this commit").count(), 1, "{}", again);
+
+    // The penalty follows the policy as it is now
+    set_late_policy(&dates, "percent_per_day = 50");
+    let steeper = run_late_class(base, &cfgdir, &["--rerun-failures"]);
+    assert!(steeper.contains("\"score\": 5"), "{}", steeper);
+    assert_eq!(steeper.matches("Late ").count(), 1, "{}", steeper);
+    set_late_policy(&dates, "percent_per_day = 50\nextensions = { alice = 24 }");
+    let excused = run_late_class(base, &cfgdir, &["--rerun-failures"]);
+    assert!(excused.contains("\"score\": 10"), "{}", excused);
+    assert!(!excused.contains("Late "), "{}", excused);
+}
+
+#[test]
+fn cli_class_incremental_rerun_failures_caches_unpenalized_result() {
+    let tmp = tempfile::tempdir().unwrap();
+    let base = tmp.path();
+    let (cfgdir, dates) = write_late_class(base);
+    set_late_policy(&dates, "percent_per_day = 20");
+    let first = run_late_class(base, &cfgdir, &[]);
+    assert!(first.contains("\"score\": 8"), "{}", first);
+
+    // The reused, penalized result is cached without its penalty
+    let cached = run_late_class(base, &cfgdir, &["--incremental", "--rerun-failures"]);
+    assert!(cached.contains("\"score\": 8"), "{}", cached);
+    let cache = fs::read_to_string(base.join("projx-due.cache")).unwrap();
+    assert!(!cache.contains("Late ") && !cache.contains("\"late\""), "{}", cache);
+
+    // A cache hit picks up an extension granted since
+    set_late_policy(&dates, "percent_per_day = 20\nextensions = { alice = 24 }");
+    let excused = run_late_class(base, &cfgdir, &["--incremental"]);
+    assert!(excused.contains("\"score\": 10"), "{}", excused);
+    assert!(!excused.contains("Late "), "{}", excused);
+    set_late_policy(&dates, "percent_per_day = 20");
+    let again = run_late_class(base, &cfgdir, &["--incremental", "--rerun-failures"]);
+    assert!(again.contains("\"score\": 8"), "{}", again);
+    assert_eq!(again.matches("Late ").count(), 1, "{}", again);
+}
@@ -0,0 +1,71 @@
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+use std::process::Command;
+
+use assert_cmd::prelude::*;
+
+mod common;
+use common::git;
+
+fn run_class(base: &Path, cfgdir: &Path) {
+    let mut cmd = Command::cargo_bin("grade-rs").unwrap();
+    cmd.arg("class").args(["-p", "projx", "--incremental"])
+        .env("HOME", base)
+        .env("GRADE_CONFIG_DIR", cfgdir)
+        .current_dir(base);
+    cmd.assert().success();
+}
+
+#[test]
+fn class_incremental_skips_unchanged_repos() {
+    let tmp = tempfile::tempdir().unwrap();
+    let base = tmp.path();
+
+    // The program records each run so the test can tell when it was re-executed
+    let repo = base.join("projx-alice");
+    fs::create_dir_all(&repo).unwrap();
+    let prog = repo.join("projx");
+    fs::write(&prog, format!("#!/bin/sh\necho run >> {}\necho ok\n", base.join("runs.log").display())).unwrap();
+    let mut perm = fs::metadata(&prog).unwrap().permissions();
+    perm.set_mode(0o755); fs::set_permissions(&prog, perm).unwrap();
+    git(&["init", "-q", "-b", "main"], &repo);
+    git(&["add", "projx"], &repo);
+    git(&["commit", "-q", "-m", "start"], &repo);
+
+    let tests = base.join("tests_repo").join("projx");
+    fs::create_dir_all(&tests).unwrap();
+    let suite = "[project]\nbuild = 'none'\n\n[[tests]]\nname = \"01\"\ninput = [\"./projx\"]\nexpected = \"ok\"\nrubric = 10\n";
+    fs::write(tests.join("projx.toml"), suite).unwrap();
+
+    let cfgdir = base.join("cfg");
+    fs::create_dir_all(&cfgdir).unwrap();
+    fs::write(cfgdir.join("config.toml"), format!("[Test]\ntests_path = \"{}\"\n[Config]\nstudents = [\"alice\"]\n", tests.parent().unwrap().display())).unwrap();
+
+    let runs = || fs::read_to_string(base.join("runs.log")).unwrap_or_default().lines().count();
+    run_class(base, &cfgdir);
+    assert_eq!(runs(), 1);
+    assert!(base.join("projx.cache").is_file());
+
+    // Unchanged HEAD and tests: cached
+    run_class(base, &cfgdir);
+    assert_eq!(runs(), 1);
+    let data = fs::read_to_string(base.join("projx.json")).unwrap();
+    assert!(data.contains("\"score\": 10"));
+    // The full SHA of the graded commit is recorded for feedback comments
+    let out = Command::new("git").args(["rev-parse", "HEAD"]).current_dir(&repo).output().unwrap();
+    let sha = String::from_utf8_lossy(&out.stdout).trim().to_string();
+    assert!(data.contains(&format!("\"commit\": \"{}\"", sha)), "{}", data);
+
+    // A new commit invalidates that student's entry
+    fs::write(repo.join("notes.txt"), "x\n").unwrap();
+    git(&["add", "notes.txt"], &repo);
+    git(&["commit", "-q", "-m", "notes"], &repo);
+    run_class(base, &cfgdir);
+    assert_eq!(runs(), 2);
+
+    // Any change in the tests directory invalidates the whole cache
+    fs::write(tests.join("input.txt"), "data\n").unwrap();
+    run_class(base, &cfgdir);
+    assert_eq!(runs(), 3);
+}
@@ -54,3 +54,122 @@ fn cli_test_subcommand_end_to_end() {
     cmd.assert().success().stdout(p_contains("10/10"));
 }
 
+
+#[test]
+fn cli_test_writes_artifact_and_step_summary() {
+    let tmp = tempfile::tempdir().unwrap();
+    let base = tmp.path();
+    let project = "projx";
+    let repo = write_mini_repo(base, project);
+    let tests_repo = write_tests_repo(base, project);
+    let cfgdir = base.join("cfg");
+    fs::create_dir_all(&cfgdir).unwrap();
+    fs::write(cfgdir.join("config.toml"), format!("[Test]\ntests_path = \"{}\"\n", tests_repo.to_string_lossy())).unwrap();
+    let summary = base.join("summary.md");
+
+    let mut cmd = Command::cargo_bin("grade-rs").unwrap();
+    cmd.arg("test").args(["-p", project, "--artifact", "grade-results.json"])
+        .env("HOME", base)
+        .env("GRADE_CONFIG_DIR", &cfgdir)
+        .env("GITHUB_STEP_SUMMARY", &summary)
+        .current_dir(&repo);
+    cmd.assert().success();
+
+    let v: serde_json::Value = serde_json::from_str(&fs::read_to_string(repo.join("grade-results.json")).unwrap()).unwrap();
+    assert_eq!(v["grade"].as_f64(), Some(10.0));
+    assert_eq!(v["results"][0]["test"], "01");
+    assert_eq!(v["results"][0]["rubric"].as_f64(), Some(10.0));
+    let md = fs::read_to_string(&summary).unwrap();
+    assert!(md.contains("## projx results: 10/10"));
+    assert!(md.contains("| ✅ 01 | 10/10 |  |"));
+}
+
+#[test]
+fn cli_workflow_generate() {
+    let tmp = tempfile::tempdir().unwrap();
+    let base = tmp.path();
+    let cfgdir = base.join("cfg");
+    fs::create_dir_all(&cfgdir).unwrap();
+    fs::write(cfgdir.join("config.toml"), "[Git]\norg = \"cs-org\"\n").unwrap();
+
+    let run = |extra: &[&str]| {
+        let mut cmd = Command::cargo_bin("grade-rs").unwrap();
+        cmd.args(["workflow", "generate", "-p", "projx"]).args(extra)
+            .env("HOME", base)
+            .env("GRADE_CONFIG_DIR", &cfgdir)
+            .current_dir(base);
+        cmd.assert()
+    };
+    run(&[]).success();
+    let yaml = fs::read_to_string(base.join(".github/workflows/grade.yml")).unwrap();
+    assert!(yaml.contains("repository: cs-org/tests"));
+    assert!(yaml.contains("cargo install --locked --git https://github.com/cs-org/autograder-rust"));
+    assert!(yaml.contains("grade-rs test -p projx --artifact grade-results.json"));
+    assert!(yaml.contains("name: grade\n"));
+    assert!(yaml.contains("${{ secrets.TESTS_TOKEN }}"));
+    assert!(!yaml.contains("checks: write"));
+    // An existing workflow is kept unless --force is given
+    run(&[]).failure();
+    run(&["--force", "--artifact-name", "results"]).success();
+    assert!(fs::read_to_string(base.join(".github/workflows/grade.yml")).unwrap().contains("name: results\n"));
+    run(&["--force", "--check-run"]).success();
+    let yaml = fs::read_to_string(base.join(".github/workflows/grade.yml")).unwrap();
+    assert!(yaml.contains("    permissions:\n      contents: read\n      checks: write\n    steps:"));
+    assert!(yaml.contains("GITHUB_TOKEN: ${{ secrets.GITHUB_TOKEN }}"));
+    assert!(yaml.contains("--artifact grade-results.json --check-run\n"));
+}
+
+#[test]
+fn cli_upload_preview_and_confirm() {
+    let server = httpmock::MockServer::start();
+    for (path, body) in [
+        ("/api/v1/courses", r#"[{"id":42,"name":"Course X"}]"#),
+        ("/api/v1/courses/42/assignments", r#"[{"id":7,"name":"projx"}]"#),
+        ("/api/v1/courses/42/enrollments", r#"[{"user_id":101,"user":{"login_id":"a123"}},{"user_id":102,"user":{"login_id":"b456"}}]"#),
+        ("/api/v1/courses/42/assignments/7/submissions/101", r#"{"score": 8.0}"#),
+        ("/api/v1/courses/42/assignments/7/submissions/102", r#"{"score": 9.0}"#),
+    ] {
+        server.mock(|when, then| {
+            when.method(httpmock::Method::GET).path(path);
+            then.status(200).header("content-type", "application/json").body(body);
+        });
+    }
+    let put = server.mock(|when, then| {
+        when.method(httpmock::Method::PUT).path("/api/v1/courses/42/assignments/7/submissions/102");
+        then.status(200);
+    });
+
+    let tmp = tempfile::tempdir().unwrap();
+    let base = tmp.path();
+    fs::write(base.join("projx.json"), r#"[{"student":"alice","score":8,"comment":"ok"},{"student":"bob","score":10,"comment":"great"},{"student":"dave","score":5,"comment":"?"}]"#).unwrap();
+    fs::write(base.join("map.csv"), "GitHub,SIS Login ID\nalice,a123\nbob,b456\n").unwrap();
+    let cfgdir = base.join("cfg");
+    fs::create_dir_all(&cfgdir).unwrap();
+    fs::write(cfgdir.join("config.toml"), format!(
+        "[Canvas]\nhost_name = \"{}\"\naccess_token = \"tok\"\ncourse_name = \"Course X\"\n\n[CanvasMapper]\nmap_path = \"{}\"\n",
+        server.base_url(), base.join("map.csv").display())).unwrap();
+
+    let upload = |extra: &[&str], stdin: &str| {
+        let mut cmd = assert_cmd::Command::cargo_bin("grade-rs").unwrap();
+        cmd.args(["upload", "-p", "projx"]).args(extra)
+            .env("HOME", base)
+            .env("GRADE_CONFIG_DIR", &cfgdir)
+            .write_stdin(stdin)
+            .current_dir(base);
+        cmd.assert()
+    };
+    let out = upload(&["--dry-run"], "").success();
+    let stdout = regex::Regex::new("\x1b\\[[0-9]+m").unwrap().replace_all(&String::from_utf8_lossy(&out.get_output().stdout), "").to_string();
+    assert!(stdout.contains("projx.json -> Course X / projx"), "{}", stdout);
+    assert!(stdout.lines().any(|l| l.starts_with("alice") && l.contains("a123") && l.ends_with("8        8  skip")), "{}", stdout);
+    assert!(stdout.lines().any(|l| l.starts_with("bob") && l.ends_with("9       10  update")), "{}", stdout);
+    assert!(stdout.lines().any(|l| l.starts_with("dave") && l.ends_with("unmapped")), "{}", stdout);
+    assert!(stdout.contains("1 to update, 1 unchanged, 1 unmapped, 0 not enrolled"));
+    put.assert_hits(0);
+
+    // Anything but yes cancels
+    upload(&["--confirm"], "n\n").success().stdout(p_contains("Upload cancelled"));
+    put.assert_hits(0);
+    upload(&["--confirm"], "y\n").success().stdout(p_contains("Uploading b456 10 "));
+    put.assert_hits(1);
+}
@@ -0,0 +1,39 @@
+// Git helpers shared by the integration tests. Commits use a fixed identity so they work
+// without a global git config.
+#![allow(dead_code)]
+
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+fn run_git(args: &[&str], cwd: &Path, author: (&str, &str), date: Option<&str>) {
+    let mut cmd = Command::new("git");
+    cmd.args(args).current_dir(cwd)
+        .env("GIT_AUTHOR_NAME", author.0).env("GIT_AUTHOR_EMAIL", author.1)
+        .env("GIT_COMMITTER_NAME", author.0).env("GIT_COMMITTER_EMAIL", author.1);
+    if let Some(d) = date { cmd.env("GIT_AUTHOR_DATE", d).env("GIT_COMMITTER_DATE", d); }
+    let ok = cmd.output().unwrap().status.success();
+    assert!(ok, "git {:?} failed", args);
+}
+
+pub fn git(args: &[&str], cwd: &Path) {
+    run_git(args, cwd, ("t", "t@example.com"), None);
+}
+
+// Write name and commit it, with the commit message set to the file name
+pub fn commit_file(repo: &Path, name: &str, text: &str) {
+    fs::write(repo.join(name), text).unwrap();
+    git(&["add", name], repo);
+    git(&["commit", "-q", "-m", name], repo);
+}
+
+// commit_file with both author and committer dates set
+pub fn commit_file_at(repo: &Path, name: &str, text: &str, date: &str) {
+    commit_as(repo, name, text, ("t", "t@example.com"), date);
+}
+
+pub fn commit_as(repo: &Path, name: &str, text: &str, author: (&str, &str), date: &str) {
+    fs::write(repo.join(name), text).unwrap();
+    git(&["add", name], repo);
+    run_git(&["commit", "-q", "-m", name], repo, author, Some(date));
+}
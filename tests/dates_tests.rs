@@ -1,6 +1,6 @@
 use std::fs;
 
-use autograder_rust::dates::Dates;
+use autograder_rust::dates::{parse_deadline, Dates};
 
 #[test]
 fn dates_from_tests_path() {
@@ -27,3 +27,38 @@ percentage = 0.5
     assert_eq!(d.items[1].suffix, "late");
 }
 
+
+#[test]
+fn late_policy_penalties() {
+    let tmp = tempfile::tempdir().unwrap();
+    let tests = tmp.path().join("tests_repo");
+    fs::create_dir_all(&tests).unwrap();
+    fs::write(tests.join("dates.toml"), r#"
+[[projx.dates]]
+suffix = "due"
+date = "2025-03-11 23:59:00"
+percentage = 1.0
+
+[projx.late_policy]
+percent_per_day = 10.0
+grace_minutes = 15
+max_days = 3
+extensions = { alice = 48.0 }
+"#).unwrap();
+
+    let d = Dates::from_tests_path(tests.to_str().unwrap(), "projx").unwrap();
+    let p = d.late_policy.unwrap();
+    assert_eq!(p.penalty("bob", -5.0), None);
+    assert_eq!(p.penalty("bob", 0.2), None);
+    let (pct, msg) = p.penalty("bob", 25.0).unwrap();
+    assert_eq!(pct, 20.0);
+    assert!(msg.contains("2 day(s)"), "{}", msg);
+    assert_eq!(p.penalty("bob", 80.0).unwrap().0, 100.0);
+    // Extension moves alice's deadline by two days
+    assert_eq!(p.penalty("alice", 47.0), None);
+    assert_eq!(p.penalty("alice", 50.0).unwrap().0, 10.0);
+
+    let due = parse_deadline("2025-03-11 23:59:00").unwrap();
+    assert_eq!(parse_deadline("2025-03-12").unwrap() - due, 60);
+    assert!(parse_deadline("March 11").is_err());
+}
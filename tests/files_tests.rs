@@ -0,0 +1,70 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use autograder_rust::config::TestCfg;
+use autograder_rust::files::glob_matches;
+use autograder_rust::testcases::{TestRunner, Repo};
+
+fn write_tests_repo(base: &Path, project: &str) -> PathBuf {
+    let tests = base.join("tests_repo").join(project);
+    fs::create_dir_all(tests.join("tests")).unwrap();
+    fs::write(tests.join("tests").join("run.sh"), "#!/bin/sh\necho harness\n").unwrap();
+    fs::write(tests.join(format!("{}.toml", project)), r#"
+[project]
+build = 'none'
+
+[project.files]
+required = ["README.md", "src/*.c"]
+forbidden = ["*.o", "a.out"]
+forbidden_patterns = ["system\\("]
+scan = ["*.c"]
+protected = ["tests/run.sh"]
+rubric = 1
+"#).unwrap();
+    tests.parent().unwrap().to_path_buf()
+}
+
+#[test]
+fn file_rules_score_each_rule() {
+    let tmp = tempfile::tempdir().unwrap();
+    let base = tmp.path();
+    let project = "projx";
+    let tests_repo = write_tests_repo(base, project);
+
+    // README present, src/main.c present but calls system(), stray object file,
+    // and an edited copy of the protected harness
+    let repo = base.join("repo");
+    fs::create_dir_all(repo.join("src")).unwrap();
+    fs::create_dir_all(repo.join("tests")).unwrap();
+    fs::write(repo.join("README.md"), "hi\n").unwrap();
+    fs::write(repo.join("src").join("main.c"), "int main() {\n  system(\"ls\");\n}\n").unwrap();
+    fs::write(repo.join("src").join("main.o"), [0u8, 1, 2]).unwrap();
+    fs::write(repo.join("tests").join("run.sh"), "#!/bin/sh\necho pass\n").unwrap();
+    // The tests repo as checked out by the generated workflow is not the student's
+    fs::create_dir_all(repo.join(".grade/tests")).unwrap();
+    fs::write(repo.join(".grade/tests/check.c"), "system(\"make\");\n").unwrap();
+    fs::write(repo.join(".grade/tests/check.o"), [0u8]).unwrap();
+
+    let cfg = TestCfg { tests_path: tests_repo.to_string_lossy().to_string(), digital_path: String::from("~/Digital/Digital.jar") };
+    let mut runner = TestRunner::new(&cfg, false, false, false, project.to_string());
+    let repo_obj = Repo::local(repo.to_string_lossy().to_string(), None);
+    let res = runner.test_repo(&repo_obj, None).unwrap();
+
+    assert_eq!(res.results.len(), 6);
+    assert_eq!(res.score, 3);
+    let err_of = |name: &str| res.results.iter().find(|r| r.test == name).and_then(|r| r.test_err.clone());
+    assert!(err_of("required:README.md").is_none());
+    assert!(err_of("forbidden:*.o").unwrap().contains("src/main.o"));
+    assert!(err_of("pattern:system\\(").unwrap().contains("src/main.c:2"));
+    assert!(err_of("protected:tests/run.sh").unwrap().contains("modified"));
+    assert!(res.results.iter().all(|r| !r.test_err.as_deref().unwrap_or("").contains(".grade")));
+    assert_eq!(runner.total_rubric(), 6);
+}
+
+#[test]
+fn glob_matching_by_name_and_path() {
+    assert!(glob_matches("*.o", "src/deep/x.o"));
+    assert!(glob_matches("src/*.c", "src/main.c"));
+    assert!(!glob_matches("src/*.c", "src/lib/util.c"));
+    assert!(glob_matches("a.out", "a.out"));
+}
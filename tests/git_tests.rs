@@ -0,0 +1,237 @@
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+use autograder_rust::config::GitCfg;
+use autograder_rust::git::{AuditKind, AuditOpts, Git, GitOutcome, TagFallback};
+use autograder_rust::testcases::Repo;
+
+mod common;
+use common::{commit_as, commit_file, commit_file_at, git};
+
+#[test]
+fn pull_reports_typed_outcomes() {
+    let tmp = tempfile::tempdir().unwrap();
+    let base = tmp.path();
+    let upstream = base.join("upstream");
+    fs::create_dir_all(&upstream).unwrap();
+    git(&["init", "-q", "-b", "main"], &upstream);
+    commit_file(&upstream, "a.txt", "a\n");
+    git(&["clone", "-q", upstream.to_str().unwrap(), "projx-alice"], base);
+
+    let g = Git::new(GitCfg::default());
+    let mut repo = Repo::student("projx".into(), "alice".into(), None, None);
+    repo.local_path = base.join("projx-alice");
+
+    assert_eq!(g.pull_repo(&repo), GitOutcome::UpToDate);
+    commit_file(&upstream, "b.txt", "b\n");
+    assert!(matches!(g.pull_repo(&repo), GitOutcome::Updated { .. }));
+
+    // Conflicting local change to a file that upstream also changed
+    commit_file(&upstream, "a.txt", "upstream\n");
+    commit_file(&repo.local_path, "a.txt", "local\n");
+    assert_eq!(g.pull_repo(&repo), GitOutcome::Conflict);
+
+    assert_eq!(g.clone_repo("projx", &repo, None, false), GitOutcome::AlreadyExists);
+    repo.local_path = base.join("projx-missing");
+    assert!(matches!(g.pull_repo(&repo), GitOutcome::Failed(_)));
+}
+
+#[test]
+fn clone_from_file_url_template() {
+    let tmp = tempfile::tempdir().unwrap();
+    let base = tmp.path();
+    let bare = base.join("srv");
+    fs::create_dir_all(&bare).unwrap();
+    let work = base.join("work");
+    fs::create_dir_all(&work).unwrap();
+    git(&["init", "-q", "-b", "main"], &work);
+    commit_file(&work, "a.txt", "a\n");
+    git(&["clone", "-q", "--bare", work.to_str().unwrap(), bare.join("projx-alice.git").to_str().unwrap()], base);
+
+    let g = Git::new(GitCfg {
+        org: "cs101".into(),
+        url_template: Some(format!("file://{}/{{project}}-{{student}}.git", bare.display())),
+        ..Default::default()
+    });
+    let mut repo = Repo::student("projx".into(), "alice".into(), None, None);
+    repo.local_path = base.join("projx-alice");
+    assert_eq!(g.clone_repo("projx", &repo, None, false), GitOutcome::Cloned);
+    assert!(repo.local_path.join("a.txt").is_file());
+
+    let mut missing = Repo::student("projx".into(), "bob".into(), None, None);
+    missing.local_path = base.join("projx-bob");
+    assert_eq!(g.clone_repo("projx", &missing, None, false), GitOutcome::NoRemote);
+}
+
+fn head(repo: &Path) -> String {
+    let out = Command::new("git").args(["rev-parse", "--short", "HEAD"]).current_dir(repo).output().unwrap();
+    String::from_utf8_lossy(&out.stdout).trim().to_string()
+}
+
+#[test]
+fn checkout_moves_existing_clones_to_deadline() {
+    let tmp = tempfile::tempdir().unwrap();
+    let base = tmp.path();
+    let upstream = base.join("upstream");
+    fs::create_dir_all(&upstream).unwrap();
+    git(&["init", "-q", "-b", "main"], &upstream);
+    commit_file_at(&upstream, "a.txt", "a\n", "2024-01-10 12:00:00");
+    let first = head(&upstream);
+    git(&["clone", "-q", upstream.to_str().unwrap(), "projx-alice"], base);
+    commit_file_at(&upstream, "b.txt", "b\n", "2024-03-10 12:00:00");
+    let tip = head(&upstream);
+
+    let g = Git::new(GitCfg::default());
+    let mut repo = Repo::student("projx".into(), "alice".into(), None, None);
+    repo.local_path = base.join("projx-alice");
+    fs::write(repo.local_path.join("NOTES.txt"), "ta notes\n").unwrap();
+
+    // New upstream commits are fetched, but the deadline predates them
+    assert_eq!(g.checkout_repo(&repo, None, Some("2024-02-01")), GitOutcome::UpToDate);
+    assert_eq!(head(&repo.local_path), first);
+    assert_eq!(g.checkout_repo(&repo, None, None), GitOutcome::CheckedOut { from: first.clone(), to: tip.clone() });
+    assert_eq!(g.checkout_repo(&repo, Some("main"), Some("2024-02-01")), GitOutcome::CheckedOut { from: tip, to: first });
+    assert_eq!(g.checkout_repo(&repo, None, Some("2023-01-01")), GitOutcome::NoCommitsBeforeDate);
+    assert!(repo.local_path.join("NOTES.txt").is_file());
+
+    // Lateness looks at the newest commit on the remote branch, not the checked out one
+    let deadline = autograder_rust::dates::parse_deadline("2024-03-09 12:00:00").unwrap();
+    let hours = Git::hours_late(&repo.local_path, deadline).unwrap();
+    assert!((hours - 24.0).abs() < 0.01, "{}", hours);
+}
+
+#[test]
+fn submission_tag_with_fallbacks() {
+    let tmp = tempfile::tempdir().unwrap();
+    let base = tmp.path();
+    let upstream = base.join("upstream");
+    fs::create_dir_all(&upstream).unwrap();
+    git(&["init", "-q", "-b", "main"], &upstream);
+    commit_file_at(&upstream, "a.txt", "a\n", "2024-01-10 12:00:00");
+    let tagged = head(&upstream);
+    commit_file_at(&upstream, "b.txt", "b\n", "2024-03-10 12:00:00");
+    git(&["clone", "-q", upstream.to_str().unwrap(), "projx-alice"], base);
+
+    let g = Git::new(GitCfg::default());
+    let mut repo = Repo::student("projx".into(), "alice".into(), None, None);
+    repo.local_path = base.join("projx-alice");
+
+    // Untagged: deadline fallback picks the last commit before the date
+    let (o, sub) = g.checkout_submission(&repo, "final", TagFallback::Deadline, Some("2024-02-01"));
+    assert_eq!(o, GitOutcome::TagMissing { hash: tagged.clone() });
+    let sub = sub.unwrap();
+    assert_eq!(sub.tag, None);
+    assert!(sub.date.starts_with("2024-01-10"), "{}", sub.date);
+    assert_eq!(g.checkout_submission(&repo, "final", TagFallback::NotSubmitted, None), (GitOutcome::NotSubmitted, None));
+
+    // Tag created after cloning is fetched
+    git(&["tag", "final", &tagged], &upstream);
+    let (o, sub) = g.checkout_submission(&repo, "final", TagFallback::NotSubmitted, None);
+    assert_eq!(o, GitOutcome::Tagged { tag: "final".into(), hash: tagged.clone() });
+    assert_eq!(sub.unwrap().tag.as_deref(), Some("final"));
+    assert_eq!(head(&repo.local_path), tagged);
+}
+
+#[test]
+fn audit_flags_authors_backdating_and_late_bulk() {
+    let tmp = tempfile::tempdir().unwrap();
+    let repo = tmp.path().join("projx-alice");
+    fs::create_dir_all(&repo).unwrap();
+    git(&["init", "-q", "-b", "main"], &repo);
+    commit_as(&repo, "start.c", "x\n", ("Prof", "prof@example.edu"), "2024-01-01T00:00:00+00:00");
+    commit_as(&repo, "a.c", "a\n", ("alice", "123+alice@users.noreply.github.com"), "2024-01-05T00:00:00+00:00");
+    commit_as(&repo, "b.c", "b\n", ("Jane", "jdoe@example.edu"), "2024-01-06T00:00:00+00:00");
+    commit_as(&repo, "c.c", "c\n", ("alice", "alice@example.com"), "2024-01-02T00:00:00+00:00");
+    let bulk: String = (0..600).map(|i| format!("{}\n", i)).collect();
+    commit_as(&repo, "d.c", &bulk, ("alice", "alice@example.com"), "2024-01-09T20:00:00+00:00");
+
+    // 2024-01-10T00:00:00Z
+    let opts = AuditOpts { allow: vec!["prof@example.edu".into()], deadline: Some(1704844800), large_lines: 500, window_hours: 24.0 };
+    // jdoe comes from the roster mapping, so Jane's commit is accepted with it
+    let kinds = |ids: &[String]| Git::audit_repo(&repo, ids, &opts).unwrap().iter().map(|f| f.kind).collect::<Vec<_>>();
+    assert_eq!(kinds(&["alice".into()]), vec![AuditKind::ForeignAuthor, AuditKind::Backdated, AuditKind::LargeBeforeDeadline]);
+    assert_eq!(kinds(&["alice".into(), "jdoe@example.edu".into()]), vec![AuditKind::Backdated, AuditKind::LargeBeforeDeadline]);
+}
+
+#[test]
+fn audit_matches_whole_emails_and_names() {
+    let tmp = tempfile::tempdir().unwrap();
+    let repo = tmp.path().join("projx-alice");
+    fs::create_dir_all(&repo).unwrap();
+    git(&["init", "-q", "-b", "main"], &repo);
+    commit_as(&repo, "a.c", "a\n", ("Alice A", "alice@example.com"), "2024-01-01T00:00:00+00:00");
+    commit_as(&repo, "b.c", "b\n", ("Mallory", "alice@elsewhere.com"), "2024-01-02T00:00:00+00:00");
+    commit_as(&repo, "c.c", "c\n", ("alice", "mallory@example.com"), "2024-01-03T00:00:00+00:00");
+    commit_as(&repo, "d.c", "d\n", ("Mallory", "456+mallory@users.noreply.github.com"), "2024-01-04T00:00:00+00:00");
+    commit_as(&repo, "e.c", "e\n", ("jdoe", "jdoe@elsewhere.com"), "2024-01-05T00:00:00+00:00");
+
+    let opts = AuditOpts { allow: vec![], deadline: None, large_lines: 500, window_hours: 24.0 };
+    // The same local part at another domain, or an email identity's local part as a name, is someone else
+    let flagged = Git::audit_repo(&repo, &["alice".into(), "alice@example.com".into(), "jdoe@example.edu".into()], &opts).unwrap();
+    let details: Vec<&str> = flagged.iter().map(|f| f.detail.as_str()).collect();
+    assert_eq!(details, vec!["Mallory <alice@elsewhere.com>", "Mallory <456+mallory@users.noreply.github.com>", "jdoe <jdoe@elsewhere.com>"]);
+}
+
+fn show(git_dir: &Path, spec: &str) -> Option<String> {
+    let out = Command::new("git").args(["--git-dir", git_dir.to_str().unwrap(), "show", spec]).output().unwrap();
+    if out.status.success() { Some(String::from_utf8_lossy(&out.stdout).to_string()) } else { None }
+}
+
+#[test]
+fn distribute_commits_and_pushes_files() {
+    let tmp = tempfile::tempdir().unwrap();
+    let base = tmp.path();
+    let work = base.join("work");
+    fs::create_dir_all(&work).unwrap();
+    git(&["init", "-q", "-b", "main"], &work);
+    commit_file(&work, "Makefile", "all:\n");
+    let bare = base.join("projx-alice.git");
+    git(&["clone", "-q", "--bare", work.to_str().unwrap(), bare.to_str().unwrap()], base);
+    git(&["clone", "-q", bare.to_str().unwrap(), "projx-alice"], base);
+    let mut repo = Repo::student("projx".into(), "alice".into(), None, None);
+    repo.local_path = base.join("projx-alice");
+    git(&["config", "user.name", "Instructor"], &repo.local_path);
+    git(&["config", "user.email", "i@example.com"], &repo.local_path);
+
+    let src = base.join("fix");
+    fs::create_dir_all(src.join(".github/workflows")).unwrap();
+    fs::write(src.join("Makefile"), "all:\n\tcc -o prog prog.c\n").unwrap();
+    fs::write(src.join(".github/workflows/grade.yml"), "name: Grade\n").unwrap();
+    let files = vec![
+        (src.join("Makefile"), String::from("Makefile")),
+        (src.join(".github/workflows/grade.yml"), String::from(".github/workflows/grade.yml")),
+    ];
+    let g = Git::new(GitCfg::default());
+
+    // Straight to the default branch, then nothing to do the second time.
+    // An unpushed local commit stays on the local branch and out of the push.
+    commit_file(&repo.local_path, "notes.txt", "local\n");
+    let local_main = head(&repo.local_path);
+    assert!(matches!(g.distribute(&repo, &files, None, "Fix Makefile"), GitOutcome::Pushed { ref branch, ref base, .. } if branch == "main" && base == "main"));
+    assert_eq!(show(&bare, "main:Makefile").as_deref(), Some("all:\n\tcc -o prog prog.c\n"));
+    assert_eq!(show(&bare, "main:.github/workflows/grade.yml").as_deref(), Some("name: Grade\n"));
+    assert_eq!(show(&bare, "main:notes.txt"), None);
+    assert_eq!(head(&repo.local_path), local_main);
+    assert_eq!(fs::read_to_string(repo.local_path.join("Makefile")).unwrap(), "all:\n");
+    assert_eq!(g.distribute(&repo, &files, None, "Fix Makefile"), GitOutcome::NoChanges);
+
+    // On a branch, leaving the clone where it was (here detached at the first commit)
+    git(&["checkout", "-q", "--detach", "HEAD~1"], &repo.local_path);
+    let detached = head(&repo.local_path);
+    fs::write(src.join("Makefile"), "all:\n\tcc -Wall -o prog prog.c\n").unwrap();
+    assert!(matches!(g.distribute(&repo, &files[..1], Some("course-update"), "Add -Wall"), GitOutcome::Pushed { ref branch, .. } if branch == "course-update"));
+    assert_eq!(show(&bare, "course-update:Makefile").as_deref(), Some("all:\n\tcc -Wall -o prog prog.c\n"));
+    assert_eq!(show(&bare, "main:Makefile").as_deref(), Some("all:\n\tcc -o prog prog.c\n"));
+    assert_eq!(head(&repo.local_path), detached);
+
+    // Distributing to the same branch again builds on it
+    fs::write(src.join("Makefile"), "all:\n\tcc -Wall -Werror -o prog prog.c\n").unwrap();
+    assert!(matches!(g.distribute(&repo, &files[..1], Some("course-update"), "Add -Werror"), GitOutcome::Pushed { .. }));
+    assert_eq!(show(&bare, "course-update~1:Makefile").as_deref(), Some("all:\n\tcc -Wall -o prog prog.c\n"));
+    assert_eq!(head(&repo.local_path), detached);
+
+    // Local edits are never swept into the commit
+    fs::write(repo.local_path.join("Makefile"), "edited\n").unwrap();
+    assert_eq!(g.distribute(&repo, &files, None, "x"), GitOutcome::Failed("uncommitted changes".into()));
+}
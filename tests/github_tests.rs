@@ -1,4 +1,6 @@
-use autograder_rust::github::Github;
+use autograder_rust::github::{feedback_body, ArtifactFilter, FeedbackAction, FeedbackMode, Github, RerunMode, Triggered, FEEDBACK_MARKER};
+use autograder_rust::actions::{Annotation, CheckRunOutput};
+use autograder_rust::testcases::{RepoResult, TcResult};
 use autograder_rust::config::GithubCfg;
 use httpmock::prelude::*;
 use zip::write::FileOptions;
@@ -53,3 +55,314 @@ fn github_action_results_mock() {
     jobs_endpoint.assert();
     artifact_zip_endpoint.assert();
 }
+
+fn results_zip(json: &str) -> Vec<u8> {
+    let mut buf = Vec::new();
+    {
+        let mut zipw = zip::ZipWriter::new(std::io::Cursor::new(&mut buf));
+        zipw.start_file("grade-results.json", FileOptions::default()).unwrap();
+        zipw.write_all(json.as_bytes()).unwrap();
+        zipw.finish().unwrap();
+    }
+    buf
+}
+
+#[test]
+fn artifact_selection_filters_and_paginates() {
+    let server = MockServer::start();
+    let base = server.base_url();
+    let art = |id: u64, name: &str, expired: bool, created: &str, run: u64, branch: &str| format!(
+        "{{\"id\":{},\"name\":\"{}\",\"expired\":{},\"created_at\":\"{}\",\"archive_download_url\":\"{}/a{}.zip\",\"workflow_run\":{{\"id\":{},\"head_branch\":\"{}\"}}}}",
+        id, name, expired, created, base, id, run, branch);
+    // Page 1: expired newest, a feature-branch run, and a different artifact name
+    let page1 = format!("{{\"artifacts\":[{},{},{}]}}",
+        art(1, "grade", true, "2025-03-12T00:00:00Z", 11, "main"),
+        art(2, "grade", false, "2025-03-11T12:00:00Z", 12, "feature"),
+        art(3, "coverage", false, "2025-03-11T11:00:00Z", 13, "main"));
+    // Page 2: one from the lint workflow, then the one we want, then an older one
+    let page2 = format!("{{\"artifacts\":[{},{},{}]}}",
+        art(4, "grade", false, "2025-03-10T18:00:00Z", 14, "main"),
+        art(5, "grade", false, "2025-03-10T12:00:00Z", 15, "main"),
+        art(6, "grade", false, "2025-03-09T12:00:00Z", 16, "main"));
+    server.mock(|when, then| {
+        when.method(GET).path("/repos/orgx/projx-alice/actions/artifacts").query_param("per_page", "100");
+        then.status(200).header("content-type", "application/json")
+            .header("Link", format!("<{}/repos/orgx/projx-alice/actions/artifacts?page=2>; rel=\"next\"", base))
+            .body(page1);
+    });
+    server.mock(|when, then| {
+        when.method(GET).path("/repos/orgx/projx-alice/actions/artifacts").query_param("page", "2");
+        then.status(200).header("content-type", "application/json").body(page2);
+    });
+    server.mock(|when, then| {
+        when.method(GET).path("/repos/orgx/projx-alice/actions/runs/14");
+        then.status(200).header("content-type", "application/json").body("{\"name\":\"Lint\",\"path\":\".github/workflows/lint.yml\"}");
+    });
+    server.mock(|when, then| {
+        when.method(GET).path("/repos/orgx/projx-alice/actions/runs/15");
+        then.status(200).header("content-type", "application/json").body("{\"name\":\"Grade\",\"path\":\".github/workflows/grade.yml\"}");
+    });
+    server.mock(|when, then| {
+        when.method(GET).path("/repos/orgx/projx-alice/actions/runs/15/jobs");
+        then.status(200).header("content-type", "application/json").body("{\"jobs\":[{\"id\":5}]}");
+    });
+    let a5 = server.mock(|when, then| { when.method(GET).path("/a5.zip"); then.status(200).body(results_zip("{\"grade\": 5.0}")); });
+
+    let cfg = GithubCfg { host_name: base.clone(), access_token: String::from("tok") };
+    let mut gh = Github::new(cfg, "orgx".into(), "projx".into(), false).unwrap();
+    gh.set_artifact_filter(ArtifactFilter {
+        name: Some("grade".into()),
+        workflow: Some("grade.yml".into()),
+        branch: Some("main".into()),
+        // 2025-03-11T00:00:00Z
+        before: Some(1741651200),
+    });
+    let rr = gh.get_action_results("alice");
+    assert_eq!(rr.score, 5);
+    a5.assert();
+}
+
+#[test]
+fn artifact_per_test_results() {
+    let server = MockServer::start();
+    let base = server.base_url();
+    server.mock(|when, then| {
+        when.method(GET).path("/repos/orgx/projx-bob/actions/artifacts");
+        then.status(200).header("content-type", "application/json")
+            .body(format!("{{\"artifacts\":[{{\"id\":1,\"archive_download_url\":\"{}/artifact.zip\",\"workflow_run\":{{\"id\":7}}}}]}}", base));
+    });
+    server.mock(|when, then| {
+        when.method(GET).path("/repos/orgx/projx-bob/actions/runs/7/jobs");
+        then.status(200).header("content-type", "application/json").body("{\"jobs\":[{\"id\":70}]}");
+    });
+    let json = r#"{"results": [
+        {"test": "01", "rubric": 10, "score": 10},
+        {"name": "02", "rubric": 15, "score": 0, "error": "Program timed out"}
+    ]}"#;
+    server.mock(|when, then| { when.method(GET).path("/artifact.zip"); then.status(200).body(results_zip(json)); });
+
+    let cfg = GithubCfg { host_name: base, access_token: String::from("tok") };
+    let gh = Github::new(cfg, "orgx".into(), "projx".into(), false).unwrap();
+    let rr = gh.get_action_results("bob");
+    assert_eq!(rr.score, 10);
+    assert_eq!(rr.results.len(), 2);
+    assert_eq!(rr.results[1].test, "02");
+    assert_eq!(rr.results[1].rubric, 15);
+    assert_eq!(rr.results[1].test_err.as_deref(), Some("Program timed out"));
+    assert!(rr.comment.starts_with("Test results from this GitHub Actions run: https://github.com/orgx/projx-bob/actions/runs/7#summary-70\n\n"));
+    assert!(rr.comment.contains("02(0/15)    Program timed out"));
+    assert!(rr.comment.ends_with("10/25"));
+}
+
+fn feedback_client(base: String) -> Github {
+    Github::new(GithubCfg { host_name: base, access_token: String::from("tok") }, "orgx".into(), "projx".into(), false).unwrap()
+}
+
+fn sample_result() -> RepoResult {
+    RepoResult {
+        comment: "01(10/10) 02(0/5)    Program timed out\n10/15".into(),
+        results: vec![
+            TcResult { test: "01".into(), rubric: 10, score: 10, ..Default::default() },
+            TcResult { test: "02".into(), rubric: 5, score: 0, test_err: Some("Program timed out".into()), ..Default::default() },
+        ],
+        score: 10, student: Some("alice".into()), build_err: None, submission: None, late: None, commit: None,
+    }
+}
+
+#[test]
+fn feedback_issue_create_update_and_unchanged() {
+    let body = feedback_body("projx", &sample_result());
+    assert!(body.starts_with(FEEDBACK_MARKER));
+    assert!(body.contains("| ❌ 02 | 0/5 | Program timed out |"));
+
+    // No issue yet: dry run only reads, a real run creates
+    let server = MockServer::start();
+    server.mock(|when, then| {
+        when.method(GET).path("/repos/orgx/projx-alice/issues").query_param("state", "open");
+        then.status(200).header("content-type", "application/json")
+            .body("[{\"number\":3,\"title\":\"Autograder results\",\"html_url\":\"pr\",\"pull_request\":{}}]");
+    });
+    let create = server.mock(|when, then| {
+        when.method(POST).path("/repos/orgx/projx-alice/issues").json_body_partial("{\"title\":\"Autograder results\"}");
+        then.status(201).header("content-type", "application/json").body("{\"html_url\":\"https://github.com/orgx/projx-alice/issues/4\"}");
+    });
+    let gh = feedback_client(server.base_url());
+    let (action, _) = gh.post_feedback("alice", FeedbackMode::Issue, &body, None, true).unwrap();
+    assert_eq!(action, FeedbackAction::Create);
+    create.assert_hits(0);
+    let (action, url) = gh.post_feedback("alice", FeedbackMode::Issue, &body, None, false).unwrap();
+    assert_eq!(action, FeedbackAction::Create);
+    assert_eq!(url.as_deref(), Some("https://github.com/orgx/projx-alice/issues/4"));
+    create.assert();
+
+    // Existing issue: identical body is left alone, a changed one is edited in place
+    let server = MockServer::start();
+    let issue = serde_json::json!([{ "number": 4, "title": "Autograder results", "body": body, "html_url": "u4" }]);
+    server.mock(|when, then| {
+        when.method(GET).path("/repos/orgx/projx-alice/issues");
+        then.status(200).header("content-type", "application/json").body(issue.to_string());
+    });
+    let patch = server.mock(|when, then| {
+        when.method(httpmock::Method::PATCH).path("/repos/orgx/projx-alice/issues/4");
+        then.status(200).header("content-type", "application/json").body("{\"html_url\":\"u4\"}");
+    });
+    let gh = feedback_client(server.base_url());
+    let (action, _) = gh.post_feedback("alice", FeedbackMode::Issue, &body, None, false).unwrap();
+    assert_eq!(action, FeedbackAction::Unchanged);
+    patch.assert_hits(0);
+    let (action, _) = gh.post_feedback("alice", FeedbackMode::Issue, "new body", None, false).unwrap();
+    assert_eq!(action, FeedbackAction::Update);
+    patch.assert();
+}
+
+#[test]
+fn feedback_commit_comment_and_pr() {
+    let server = MockServer::start();
+    server.mock(|when, then| {
+        when.method(GET).path("/repos/orgx/projx-alice/commits/abc123/comments");
+        then.status(200).header("content-type", "application/json").body("[{\"id\":1,\"body\":\"same\",\"html_url\":\"c1\"}]");
+    });
+    let post = server.mock(|when, then| {
+        when.method(POST).path("/repos/orgx/projx-alice/commits/abc123/comments");
+        then.status(201).header("content-type", "application/json").body("{\"html_url\":\"c2\"}");
+    });
+    let gh = feedback_client(server.base_url());
+    assert!(gh.post_feedback("alice", FeedbackMode::CommitComment, "same", None, false).is_err());
+    assert_eq!(gh.post_feedback("alice", FeedbackMode::CommitComment, "same", Some("abc123"), false).unwrap().0, FeedbackAction::Unchanged);
+    let (action, url) = gh.post_feedback("alice", FeedbackMode::CommitComment, "other", Some("abc123"), false).unwrap();
+    assert_eq!((action, url.as_deref()), (FeedbackAction::Create, Some("c2")));
+    post.assert();
+
+    // PR mode edits our earlier comment on the Feedback pull request
+    server.mock(|when, then| {
+        when.method(GET).path("/repos/orgx/projx-alice/pulls");
+        then.status(200).header("content-type", "application/json").body("[{\"number\":1,\"title\":\"Feedback\",\"html_url\":\"p1\"}]");
+    });
+    let old = format!("{}\nold", FEEDBACK_MARKER);
+    server.mock(|when, then| {
+        when.method(GET).path("/repos/orgx/projx-alice/issues/1/comments");
+        then.status(200).header("content-type", "application/json")
+            .body(serde_json::json!([{ "id": 8, "body": "thanks!", "html_url": "h8" }, { "id": 9, "body": old, "html_url": "h9" }]).to_string());
+    });
+    let edit = server.mock(|when, then| {
+        when.method(httpmock::Method::PATCH).path("/repos/orgx/projx-alice/issues/comments/9");
+        then.status(200).header("content-type", "application/json").body("{\"html_url\":\"h9\"}");
+    });
+    let (action, _) = gh.post_feedback("alice", FeedbackMode::Pr, &format!("{}\nnew", FEEDBACK_MARKER), None, false).unwrap();
+    assert_eq!(action, FeedbackAction::Update);
+    edit.assert();
+}
+
+#[test]
+fn rerun_failed_jobs_then_grade_new_attempt() {
+    let server = MockServer::start();
+    let gh = feedback_client(server.base_url());
+    let runs = server.mock(|when, then| {
+        when.method(GET).path("/repos/orgx/projx-alice/actions/runs");
+        then.status(200).header("content-type", "application/json").body(r#"{"workflow_runs":[
+            {"id":9,"name":"Lint","path":".github/workflows/lint.yml","status":"completed","run_attempt":1},
+            {"id":7,"name":"Grade","path":".github/workflows/grade.yml","status":"completed","conclusion":"failure","run_attempt":1}]}"#);
+    });
+    let rerun = server.mock(|when, then| {
+        when.method(POST).path("/repos/orgx/projx-alice/actions/runs/7/rerun-failed-jobs");
+        then.status(201).body("{}");
+    });
+    let run = server.mock(|when, then| {
+        when.method(GET).path("/repos/orgx/projx-alice/actions/runs/7");
+        then.status(200).header("content-type", "application/json").body(r#"{"id":7,"name":"Grade","status":"completed","conclusion":"success",
+            "run_attempt":2,"run_started_at":"2026-10-01T12:00:00Z","html_url":"https://github.com/orgx/projx-alice/actions/runs/7"}"#);
+    });
+    // The first attempt's artifact is still listed for the run, but predates the new attempt
+    let artifacts = server.mock(|when, then| {
+        when.method(GET).path("/repos/orgx/projx-alice/actions/runs/7/artifacts");
+        then.status(200).header("content-type", "application/json").body(format!(r#"{{"artifacts":[
+            {{"id":1,"name":"grade","created_at":"2026-10-01T11:00:00Z","archive_download_url":"{0}/dl/old","workflow_run":{{"id":7}}}},
+            {{"id":2,"name":"grade","created_at":"2026-10-01T12:05:00Z","archive_download_url":"{0}/dl/new","workflow_run":{{"id":7}}}}]}}"#, server.base_url()));
+    });
+    let jobs = server.mock(|when, then| {
+        when.method(GET).path("/repos/orgx/projx-alice/actions/runs/7/jobs");
+        then.status(200).header("content-type", "application/json").body(r#"{"jobs":[{"id":3}]}"#);
+    });
+    let new_zip = server.mock(|when, then| {
+        when.method(GET).path("/dl/new");
+        then.status(200).body(results_zip(r#"{"grade":15}"#));
+    });
+
+    let deadline = std::time::Instant::now() + std::time::Duration::from_secs(5);
+    let rr = gh.rerun_results("alice", RerunMode::Failed, Some("grade.yml"), None, deadline, std::time::Duration::from_millis(10));
+    assert_eq!(rr.score, 15);
+    assert!(rr.comment.ends_with("runs/7#summary-3"), "{}", rr.comment);
+    runs.assert();
+    rerun.assert();
+    run.assert();
+    artifacts.assert();
+    jobs.assert();
+    new_zip.assert();
+}
+
+#[test]
+fn dispatch_finds_the_new_run() {
+    let server = MockServer::start();
+    let gh = feedback_client(server.base_url());
+    let mut before = server.mock(|when, then| {
+        when.method(GET).path("/repos/orgx/projx-bob/actions/workflows/grade.yml/runs").query_param("event", "workflow_dispatch");
+        then.status(200).header("content-type", "application/json").body(r#"{"workflow_runs":[{"id":20,"status":"completed"}]}"#);
+    });
+    let repo = server.mock(|when, then| {
+        when.method(GET).path("/repos/orgx/projx-bob");
+        then.status(200).header("content-type", "application/json").body(r#"{"default_branch":"main"}"#);
+    });
+    let dispatch = server.mock(|when, then| {
+        when.method(POST).path("/repos/orgx/projx-bob/actions/workflows/grade.yml/dispatches").json_body(serde_json::json!({ "ref": "main" }));
+        then.status(204);
+    });
+
+    let t = gh.rerun("bob", RerunMode::Dispatch, Some(".github/workflows/grade.yml"), None).unwrap();
+    assert!(matches!(t, Triggered::Dispatch { ref workflow, after_id: 20 } if workflow == "grade.yml"));
+    before.assert();
+    repo.assert();
+    dispatch.assert();
+
+    // Nothing new yet, then the dispatched run shows up still in progress
+    assert!(gh.triggered_run("bob", &t).unwrap().is_none());
+    before.delete();
+    server.mock(|when, then| {
+        when.method(GET).path("/repos/orgx/projx-bob/actions/workflows/grade.yml/runs");
+        then.status(200).header("content-type", "application/json").body(r#"{"workflow_runs":[
+            {"id":26,"status":"queued"},{"id":25,"status":"in_progress","html_url":"u25"},{"id":20,"status":"completed"}]}"#);
+    });
+    let run = gh.wait_for_run("bob", &t, std::time::Instant::now(), std::time::Duration::from_millis(10)).unwrap().unwrap();
+    assert_eq!(run.id, 25);
+    assert!(!run.is_completed());
+    assert_eq!(RerunMode::parse("failed").unwrap(), RerunMode::Failed);
+    assert!(RerunMode::parse("sometimes").is_err());
+}
+
+fn annotation_count(req: &HttpMockRequest) -> usize {
+    let body: serde_json::Value = serde_json::from_slice(req.body.as_deref().unwrap_or_default()).unwrap_or_default();
+    body["output"]["annotations"].as_array().map(|a| a.len()).unwrap_or(0)
+}
+
+#[test]
+fn check_run_sends_annotations_in_batches() {
+    let server = MockServer::start();
+    let gh = feedback_client(server.base_url());
+    let create = server.mock(|when, then| {
+        when.method(POST).path("/repos/orgx/projx-alice/check-runs")
+            .json_body_partial(r#"{"name":"grade-rs","head_sha":"abc123","status":"completed","conclusion":"failure","output":{"title":"0/60 points"}}"#)
+            .matches(|req| annotation_count(req) == 50);
+        then.status(201).header("content-type", "application/json").body(r#"{"id":77,"html_url":"https://github.com/orgx/projx-alice/runs/77"}"#);
+    });
+    let update = server.mock(|when, then| {
+        when.method(httpmock::Method::PATCH).path("/repos/orgx/projx-alice/check-runs/77").matches(|req| annotation_count(req) == 10);
+        then.status(200).body("{}");
+    });
+    let annotations = (1..=60).map(|i| Annotation {
+        path: "main.c".into(), start_line: i, end_line: i, annotation_level: "failure".into(), title: None, message: format!("error {}", i),
+    }).collect();
+    let output = CheckRunOutput { title: "0/60 points".into(), summary: "## projx results: 0/60".into(), annotations };
+    let url = gh.create_check_run("orgx/projx-alice", "abc123", "grade-rs", "failure", &output).unwrap();
+    assert_eq!(url, "https://github.com/orgx/projx-alice/runs/77");
+    create.assert();
+    update.assert();
+}
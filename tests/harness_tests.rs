@@ -0,0 +1,105 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+
+use autograder_rust::config::TestCfg;
+use autograder_rust::harness::{parse_junit, parse_tap, score_cases};
+use autograder_rust::testcases::{TestRunner, Repo};
+
+#[test]
+fn junit_failures_and_messages() {
+    let xml = r#"<?xml version="1.0"?>
+<testsuites>
+  <testsuite name="s">
+    <testcase classname="ListTest" name="push"/>
+    <testcase classname="ListTest" name="pop"><failure message="expected 3">got 2</failure></testcase>
+    <testcase classname="ListTest" name="len"><error message="segfault"/></testcase>
+  </testsuite>
+</testsuites>"#;
+    let cases = parse_junit(xml).unwrap();
+    assert_eq!(cases.len(), 3);
+    assert_eq!(cases[0].name, "ListTest.push");
+    assert!(cases[0].passed);
+    assert!(!cases[1].passed);
+    assert_eq!(cases[1].message.as_deref(), Some("expected 3\ngot 2"));
+    assert_eq!(cases[2].message.as_deref(), Some("segfault"));
+}
+
+#[test]
+fn tap_directives_and_diagnostics() {
+    let tap = "1..4\nok 1 - adds\nnot ok 2 - subtracts\n# expected 1, got 2\nok 3 - skipped # SKIP no network\nnot ok 4 # TODO later\n";
+    let cases = parse_tap(tap);
+    assert_eq!(cases.len(), 4);
+    assert_eq!(cases[1].name, "subtracts");
+    assert_eq!(cases[1].message.as_deref(), Some("expected 1, got 2"));
+    assert!(cases[2].passed);
+    assert_eq!(cases[3].name, "4");
+    assert!(cases[3].passed);
+}
+
+#[test]
+fn tap_plan_counts_unreported_cases_as_failed() {
+    // Harness crashed after two cases; the plan promised four
+    let cases = parse_tap("1..4\nok 1 - adds\nok 2 - subtracts\n");
+    assert_eq!(cases.len(), 4);
+    assert_eq!(cases[2].name, "3");
+    assert!(!cases[2].passed && !cases[3].passed);
+    assert_eq!(cases[3].message.as_deref(), Some("Not reported"));
+    let results = score_cases("unit", &cases, &BTreeMap::new(), 10);
+    assert_eq!(results.iter().map(|r| r.score).sum::<i64>(), 6);
+    assert_eq!(results.iter().map(|r| r.rubric).sum::<i64>(), 10);
+
+    // The plan may also come last
+    assert_eq!(parse_tap("ok 1\nnot ok 2\n1..3\n").len(), 3);
+}
+
+#[test]
+fn harness_kinds_score_per_case() {
+    let tmp = tempfile::tempdir().unwrap();
+    let base = tmp.path();
+    let project = "projx";
+
+    let repo = base.join("repo");
+    fs::create_dir_all(&repo).unwrap();
+    let prog = repo.join("run_tests");
+    fs::write(&prog, r#"#!/bin/sh
+if [ "$1" = "--junit" ]; then
+  printf '<testsuite><testcase name="a"/><testcase name="b"><failure message="boom"/></testcase></testsuite>' > report.xml
+  exit 1
+fi
+printf '1..3\nok 1 - one\nok 2 - two\nnot ok 3 - three\n'
+"#).unwrap();
+    let mut perm = fs::metadata(&prog).unwrap().permissions();
+    perm.set_mode(0o755); fs::set_permissions(&prog, perm).unwrap();
+
+    let tests = base.join("tests_repo").join(project);
+    fs::create_dir_all(&tests).unwrap();
+    fs::write(tests.join(format!("{}.toml", project)), r#"
+[project]
+build = 'none'
+
+[[tests]]
+name = "tap"
+kind = "tap"
+input = ["./run_tests"]
+rubric = 10
+
+[[tests]]
+name = "junit"
+kind = "junit"
+input = ["./run_tests", "--junit"]
+output = "report.xml"
+points = { a = 4, b = 6 }
+"#).unwrap();
+
+    let cfg = TestCfg { tests_path: tests.parent().unwrap().to_string_lossy().to_string(), digital_path: String::from("~/Digital/Digital.jar") };
+    let mut runner = TestRunner::new(&cfg, false, false, false, project.to_string());
+    let res = runner.test_repo(&Repo::local(repo.to_string_lossy().to_string(), None), None).unwrap();
+
+    // TAP splits 10 as 4/3/3; third fails. JUnit: a passes (4), b fails (6)
+    let names: Vec<&str> = res.results.iter().map(|r| r.test.as_str()).collect();
+    assert_eq!(names, vec!["tap/one", "tap/two", "tap/three", "junit/a", "junit/b"]);
+    assert_eq!(res.score, 4 + 3 + 4);
+    assert_eq!(runner.total_rubric(), 20);
+    assert_eq!(res.results[4].test_err.as_deref(), Some("boom"));
+}
@@ -14,8 +14,8 @@ fn histogram_and_write_json() {
     let runner = TestRunner::new(&cfg, false, false, false, project.clone());
 
     // Two fake results with scores 3 and 7
-    let rr1 = RepoResult { student: Some("alice".into()), score: 3, results: vec![TcResult{rubric:3, score:3, test:"01".into(), test_err: None}], comment: String::new(), build_err: None };
-    let rr2 = RepoResult { student: Some("bob".into()), score: 7, results: vec![TcResult{rubric:7, score:7, test:"01".into(), test_err: None}], comment: String::new(), build_err: None };
+    let rr1 = RepoResult { student: Some("alice".into()), score: 3, results: vec![TcResult{rubric:3, score:3, test:"01".into(), test_err: None, ..Default::default()}], comment: String::new(), build_err: None, submission: None, late: None, commit: None };
+    let rr2 = RepoResult { student: Some("bob".into()), score: 7, results: vec![TcResult{rubric:7, score:7, test:"01".into(), test_err: None, ..Default::default()}], comment: String::new(), build_err: None, submission: None, late: None, commit: None };
     let class_results = vec![rr1, rr2];
 
     // Print histogram (smoke test: just ensure it doesn't panic)
@@ -0,0 +1,58 @@
+use std::fs;
+use std::path::Path;
+
+use autograder_rust::config::TestCfg;
+use autograder_rust::history::repo_history;
+use autograder_rust::testcases::{Repo, TestRunner};
+
+mod common;
+use common::{commit_file_at, git};
+
+fn init_repo(path: &Path) {
+    fs::create_dir_all(path).unwrap();
+    git(&["init", "-q", "-b", "main"], path);
+    commit_file_at(path, "a.c", "1\n2\n", "2024-01-10T09:00:00+00:00");
+    commit_file_at(path, "a.c", "1\n2\n3\n", "2024-01-10T15:00:00+00:00");
+    commit_file_at(path, "b.c", "1\n2\n3\n4\n5\n", "2024-01-12T09:00:00+00:00");
+}
+
+#[test]
+fn history_counts_commits_days_and_lines() {
+    let tmp = tempfile::tempdir().unwrap();
+    let repo = tmp.path().join("projx-alice");
+    init_repo(&repo);
+
+    let h = repo_history("alice", &repo).unwrap();
+    assert_eq!(h.commits, 3);
+    assert_eq!(h.active_days, 2);
+    assert!(h.first_commit.unwrap().starts_with("2024-01-10T09:00:00"));
+    assert!(h.last_commit.unwrap().starts_with("2024-01-12"));
+    assert_eq!((h.lines_added, h.lines_removed), (8, 0));
+    assert_eq!(h.largest_commit_lines, 5);
+}
+
+#[test]
+fn min_commits_scored_in_class() {
+    let tmp = tempfile::tempdir().unwrap();
+    let base = tmp.path();
+    let repo = base.join("repo");
+    init_repo(&repo);
+
+    let tests = base.join("tests_repo").join("projx");
+    fs::create_dir_all(&tests).unwrap();
+    let write_suite = |min: usize| fs::write(tests.join("projx.toml"), format!("[project]\nbuild = 'none'\nmin_commits = {}\nmin_commits_rubric = 5\n", min)).unwrap();
+    let cfg = TestCfg { tests_path: tests.parent().unwrap().to_string_lossy().to_string(), digital_path: String::from("~/Digital/Digital.jar") };
+
+    write_suite(3);
+    let mut runner = TestRunner::new(&cfg, false, false, false, "projx".into());
+    let res = runner.test_repo(&Repo::local(repo.to_string_lossy().to_string(), None), None).unwrap();
+    assert_eq!(res.results[0].test, "commits");
+    assert_eq!(res.score, 5);
+    assert_eq!(runner.total_rubric(), 5);
+
+    write_suite(4);
+    let mut runner = TestRunner::new(&cfg, false, false, false, "projx".into());
+    let res = runner.test_repo(&Repo::local(repo.to_string_lossy().to_string(), None), None).unwrap();
+    assert_eq!(res.score, 0);
+    assert_eq!(res.results[0].test_err.as_deref(), Some("3 commits, at least 4 required"));
+}
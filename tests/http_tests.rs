@@ -0,0 +1,105 @@
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use autograder_rust::http::{Http, HttpOpts};
+use reqwest::header::HeaderMap;
+
+// Serves the given raw responses in order, one per connection, and records each request's headers
+fn scripted_server(responses: Vec<String>) -> (String, Arc<Mutex<Vec<String>>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let base = format!("http://{}", listener.local_addr().unwrap());
+    let seen = Arc::new(Mutex::new(vec![]));
+    let seen2 = seen.clone();
+    std::thread::spawn(move || {
+        for resp in responses {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut head = String::new();
+            loop {
+                let mut line = String::new();
+                if reader.read_line(&mut line).unwrap() == 0 || line == "\r\n" { break; }
+                head.push_str(&line);
+            }
+            seen2.lock().unwrap().push(head.to_lowercase());
+            stream.write_all(resp.as_bytes()).unwrap();
+        }
+    });
+    (base, seen)
+}
+
+fn response(status: &str, headers: &[&str], body: &str) -> String {
+    let mut r = format!("HTTP/1.1 {}\r\nconnection: close\r\ncontent-length: {}\r\n", status, body.len());
+    for h in headers { r.push_str(h); r.push_str("\r\n"); }
+    r.push_str("\r\n");
+    r.push_str(body);
+    r
+}
+
+fn fast() -> HttpOpts { HttpOpts { retries: 3, backoff: Duration::from_millis(5), ..Default::default() } }
+
+#[test]
+fn retries_server_errors_and_throttling() {
+    let (base, seen) = scripted_server(vec![
+        response("503 Service Unavailable", &[], ""),
+        response("429 Too Many Requests", &["retry-after: 0"], ""),
+        response("403 Forbidden", &["x-ratelimit-remaining: 0", "x-ratelimit-reset: 0"], "{}"),
+        response("200 OK", &[], "{\"ok\": true}"),
+    ]);
+    let http = Http::new(HeaderMap::new(), fast()).unwrap();
+    let res = http.get(&format!("{}/x", base)).unwrap();
+    assert_eq!(res.status().as_u16(), 200);
+    let v: serde_json::Value = res.json().unwrap();
+    assert_eq!(v["ok"], true);
+    assert_eq!(seen.lock().unwrap().len(), 4);
+}
+
+#[test]
+fn gives_up_after_retries() {
+    let (base, seen) = scripted_server(vec![
+        response("502 Bad Gateway", &[], ""),
+        response("502 Bad Gateway", &[], ""),
+    ]);
+    let opts = HttpOpts { retries: 1, ..fast() };
+    let http = Http::new(HeaderMap::new(), opts).unwrap();
+    let res = http.get(&format!("{}/x", base)).unwrap();
+    assert_eq!(res.status().as_u16(), 502);
+    assert_eq!(seen.lock().unwrap().len(), 2);
+}
+
+#[test]
+fn put_retries_only_when_throttled() {
+    let (base, seen) = scripted_server(vec![
+        response("429 Too Many Requests", &["retry-after: 0"], ""),
+        response("200 OK", &[], "{}"),
+        response("500 Internal Server Error", &[], ""),
+    ]);
+    let http = Http::new(HeaderMap::new(), fast()).unwrap();
+    let params = [("submission[posted_grade]", String::from("10"))];
+    assert_eq!(http.put_form(&format!("{}/x", base), &params).unwrap().status().as_u16(), 200);
+    // The PUT may have been applied before the 500, so it is not sent again
+    assert_eq!(http.put_form(&format!("{}/x", base), &params).unwrap().status().as_u16(), 500);
+    assert_eq!(seen.lock().unwrap().len(), 3);
+}
+
+#[test]
+fn etag_cache_serves_not_modified() {
+    let tmp = tempfile::tempdir().unwrap();
+    let (base, seen) = scripted_server(vec![
+        response("200 OK", &["etag: \"v1\"", "link: </x?page=2>; rel=\"next\""], "[1,2]"),
+        response("304 Not Modified", &["etag: \"v1\""], ""),
+    ]);
+    let mut http = Http::new(HeaderMap::new(), fast()).unwrap();
+    http.set_cache_dir(Some(tmp.path().to_path_buf()));
+    let url = format!("{}/x", base);
+    let first: Vec<i32> = http.get(&url).unwrap().json().unwrap();
+    let again = http.get(&url).unwrap();
+    assert_eq!(again.status().as_u16(), 200);
+    assert_eq!(again.json::<Vec<i32>>().unwrap(), first);
+    // The pagination link survives a cached response
+    assert!(again.headers().get("link").is_some());
+    let seen = seen.lock().unwrap();
+    assert!(!seen[0].contains("if-none-match"));
+    assert!(seen[1].contains("if-none-match: \"v1\""));
+}
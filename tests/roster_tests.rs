@@ -0,0 +1,50 @@
+use std::fs;
+
+use autograder_rust::config::{ConfigCfg, GithubCfg};
+use autograder_rust::github::Github;
+use autograder_rust::roster::{merge, read_roster, write_roster};
+use httpmock::prelude::*;
+
+#[test]
+fn lists_project_repos_across_pages() {
+    let server = MockServer::start();
+    let base = server.base_url();
+    let page1 = server.mock(|when, then| {
+        when.method(GET).path("/orgs/orgx/repos").query_param("per_page", "100");
+        then.status(200)
+            .header("content-type", "application/json")
+            .header("Link", format!("<{}/orgs/orgx/repos?page=2>; rel=\"next\"", base))
+            .body(r#"[{"name":"projx-carol"},{"name":"other-alice"},{"name":"projx-alice"}]"#);
+    });
+    let page2 = server.mock(|when, then| {
+        when.method(GET).path("/orgs/orgx/repos").query_param("page", "2");
+        then.status(200)
+            .header("content-type", "application/json")
+            .body(r#"[{"name":"projx-bob"},{"name":"projx-"}]"#);
+    });
+
+    let gh = Github::new(GithubCfg { host_name: base, access_token: "tok".into() }, "orgx".into(), "projx".into(), false).unwrap();
+    assert_eq!(gh.list_project_students().unwrap(), vec!["alice", "bob", "carol"]);
+    page1.assert();
+    page2.assert();
+}
+
+#[test]
+fn roster_merge_and_fallback() {
+    let tmp = tempfile::tempdir().unwrap();
+    let path = tmp.path().join("roster.txt");
+    fs::write(&path, "# section 1\nalice\n\ndave  # dropped\n").unwrap();
+    let existing = read_roster(path.to_str().unwrap()).unwrap();
+    assert_eq!(existing, vec!["alice", "dave"]);
+
+    let diff = merge(&existing, &["carol".into(), "alice".into(), "bob".into()]);
+    assert_eq!(diff.added, vec!["bob", "carol"]);
+    assert_eq!(diff.missing, vec!["dave"]);
+    assert_eq!(diff.merged, vec!["alice", "dave", "bob", "carol"]);
+    write_roster(path.to_str().unwrap(), &diff.merged).unwrap();
+
+    let cfg = ConfigCfg { students: vec![], roster: Some(path.to_string_lossy().to_string()), ..Default::default() };
+    assert_eq!(cfg.student_list(), vec!["alice", "dave", "bob", "carol"]);
+    let explicit = ConfigCfg { students: vec!["zed".into()], ..cfg };
+    assert_eq!(explicit.student_list(), vec!["zed"]);
+}
@@ -80,3 +80,173 @@ fn test_runner_end_to_end() {
     assert_eq!(res.score, 10);
     assert_eq!(res.results.len(), 3);
 }
+
+#[test]
+fn time_limits_award_best_satisfied_tier() {
+    let tmp = tempfile::tempdir().unwrap();
+    let base = tmp.path().to_path_buf();
+    let project = "projx";
+    let repo = write_mini_repo(&base, project);
+    let tests = base.join("tests_repo").join(project);
+    fs::create_dir_all(&tests).unwrap();
+    // A shell script can't finish in under 1 ms, so only the slower tier is earned
+    fs::write(tests.join(format!("{}.toml", project)), r#"
+[project]
+build = 'none'
+
+[[tests]]
+name = "01"
+input = ["./$project"]
+expected = "ok"
+rubric = 2
+repeat = 3
+time_limits = [{under_ms = 1, points = 5}, {under_ms = 60000, points = 2}]
+"#).unwrap();
+
+    let cfg = TestCfg { tests_path: tests.parent().unwrap().to_string_lossy().to_string(), digital_path: String::from("~/Digital/Digital.jar") };
+    let mut runner = TestRunner::new(&cfg, false, false, false, project.to_string());
+    let res = runner.test_repo(&Repo::local(repo.to_string_lossy().to_string(), None), None).unwrap();
+    assert_eq!(res.results.len(), 2);
+    let timed = &res.results[1];
+    assert_eq!(timed.test, "01/time");
+    assert_eq!((timed.score, timed.rubric), (2, 5));
+    assert!(timed.duration_ms.is_some());
+    assert_eq!(res.score, 4);
+    assert_eq!(runner.total_rubric(), 7);
+}
+
+#[test]
+fn retries_flag_flaky_and_retest_only_failures() {
+    let tmp = tempfile::tempdir().unwrap();
+    let base = tmp.path().to_path_buf();
+    let project = "projx";
+    let repo = base.join("repo");
+    fs::create_dir_all(&repo).unwrap();
+    // Fails on its first run only; every run is counted
+    let prog = repo.join("flaky");
+    fs::write(&prog, "#!/bin/sh\nn=$(cat count 2>/dev/null || echo 0)\nn=$((n+1))\necho $n > count\nif [ $n -lt 2 ]; then echo bad; else echo ok; fi\n").unwrap();
+    let mut perm = fs::metadata(&prog).unwrap().permissions();
+    perm.set_mode(0o755);
+    fs::set_permissions(&prog, perm).unwrap();
+    let tests = base.join("tests_repo").join(project);
+    fs::create_dir_all(&tests).unwrap();
+    fs::write(tests.join(format!("{}.toml", project)), r#"
+[project]
+build = 'none'
+retries = 2
+
+[[tests]]
+name = "01"
+input = ["./flaky"]
+expected = "ok"
+rubric = 3
+
+[[tests]]
+name = "02"
+input = ["echo", "fixed"]
+expected = "fixed"
+rubric = 4
+"#).unwrap();
+
+    let cfg = TestCfg { tests_path: tests.parent().unwrap().to_string_lossy().to_string(), digital_path: String::from("~/Digital/Digital.jar") };
+    let mut runner = TestRunner::new(&cfg, false, false, false, project.to_string());
+    let repo_obj = Repo::local(repo.to_string_lossy().to_string(), None);
+    let res = runner.test_repo(&repo_obj, None).unwrap();
+    assert_eq!(res.score, 7);
+    assert!(res.results[0].flaky);
+    assert_eq!(res.results[0].attempts, Some(2));
+    assert!(!res.results[1].flaky);
+
+    // Pretend 02 failed last time: only it is re-executed, 01 is kept as recorded
+    let mut previous = res.clone();
+    previous.results[1].score = 0;
+    previous.score = 3;
+    let again = runner.retest_failures(&repo_obj, &previous).unwrap();
+    assert_eq!(again.score, 7);
+    assert_eq!(fs::read_to_string(repo.join("count")).unwrap().trim(), "2");
+}
+
+#[test]
+fn includes_and_defaults_compose_suites() {
+    let tmp = tempfile::tempdir().unwrap();
+    let base = tmp.path().to_path_buf();
+    let project = "projx";
+    let repo = write_mini_repo(&base, project);
+    let tests_repo = base.join("tests_repo");
+    let tests = tests_repo.join(project);
+    fs::create_dir_all(tests_repo.join("common")).unwrap();
+    fs::create_dir_all(&tests).unwrap();
+    fs::write(tests_repo.join("common").join("build.toml"), r#"
+[[tests]]
+name = "01"
+input = ["./$project"]
+expected = "OK"
+"#).unwrap();
+    fs::write(tests.join(format!("{}.toml", project)), r#"
+include = ["../common/build.toml"]
+
+[defaults]
+rubric = 5
+case_sensitive = true
+
+[project]
+build = 'none'
+
+[[tests]]
+name = "02"
+input = ["./$project", "hello"]
+expected = "hello"
+rubric = 3
+"#).unwrap();
+
+    let cfg = TestCfg { tests_path: tests_repo.to_string_lossy().to_string(), digital_path: String::from("~/Digital/Digital.jar") };
+    let mut runner = TestRunner::new(&cfg, false, false, false, project.to_string());
+    let repo_obj = Repo::local(repo.to_string_lossy().to_string(), None);
+    let res = runner.test_repo(&repo_obj, None).unwrap();
+    // 01 inherits rubric 5 and case sensitivity ("ok" != "OK"); 02 keeps its own rubric
+    assert_eq!(res.results[0].test, "01");
+    assert_eq!((res.results[0].score, res.results[0].rubric), (0, 5));
+    assert_eq!(res.score, 3);
+
+    // A duplicate name and an include cycle are both reported as errors
+    fs::write(tests_repo.join("common").join("build.toml"), "include = [\"../projx/projx.toml\"]\n").unwrap();
+    let err = runner.test_repo(&repo_obj, None).unwrap_err().to_string();
+    assert!(err.contains("Include cycle"), "{}", err);
+    fs::write(tests_repo.join("common").join("build.toml"), "[[tests]]\nname = \"02\"\ninput = [\"true\"]\n").unwrap();
+    let err = runner.test_repo(&repo_obj, None).unwrap_err().to_string();
+    assert!(err.contains("Duplicate test name \"02\""), "{}", err);
+
+    // Two includes sharing a file load its tests once
+    fs::write(tests_repo.join("common").join("build.toml"), "[[tests]]\nname = \"01\"\ninput = [\"./$project\"]\nexpected = \"OK\"\n").unwrap();
+    for part in ["a", "b"] {
+        fs::write(tests_repo.join("common").join(format!("{}.toml", part)), format!("include = [\"build.toml\"]\n\n[[tests]]\nname = \"{}\"\ninput = [\"true\"]\n", part)).unwrap();
+    }
+    fs::write(tests.join(format!("{}.toml", project)), "include = [\"../common/a.toml\", \"../common/b.toml\"]\n\n[project]\nbuild = 'none'\n").unwrap();
+    let res = runner.test_repo(&repo_obj, None).unwrap();
+    let names: Vec<&str> = res.results.iter().map(|r| r.test.as_str()).collect();
+    assert_eq!(names, vec!["01", "a", "b"]);
+}
+
+#[test]
+fn time_limits_only_on_output_tests() {
+    let tmp = tempfile::tempdir().unwrap();
+    let base = tmp.path().to_path_buf();
+    let repo = write_mini_repo(&base, "projx");
+    let tests = base.join("tests_repo").join("projx");
+    fs::create_dir_all(&tests).unwrap();
+    fs::write(tests.join("projx.toml"), r#"
+[project]
+build = 'none'
+
+[[tests]]
+name = "unit"
+kind = "tap"
+input = ["true"]
+rubric = 5
+time_limits = [{ under_ms = 1000, points = 2 }]
+"#).unwrap();
+    let cfg = TestCfg { tests_path: base.join("tests_repo").to_string_lossy().to_string(), digital_path: String::from("~/Digital/Digital.jar") };
+    let mut runner = TestRunner::new(&cfg, false, false, false, "projx".into());
+    let err = runner.test_repo(&Repo::local(repo.to_string_lossy().to_string(), None), None).unwrap_err().to_string();
+    assert!(err.contains("test \"unit\" of kind \"tap\" cannot have time_limits"), "{}", err);
+}